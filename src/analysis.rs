@@ -0,0 +1,307 @@
+use anyhow::{anyhow, Context, Result};
+
+use crate::{
+    euclidean::PointProcess,
+    statistics::Accumulator,
+    store::{Key, Store},
+};
+
+/// Weighted least-squares fit of `y ≈ Σ_k β_k x_k`
+#[derive(Clone, Debug)]
+pub struct Fit {
+    pub coefficients: Vec<f64>,
+    pub covariance: Vec<Vec<f64>>,
+    /// Sum of the squared residuals, in units of their errors
+    pub chi2: f64,
+    /// Degrees of freedom, the points minus the coefficients
+    pub dof: usize,
+}
+
+impl Fit {
+    /// Standard error of the `k`th coefficient
+    pub fn error(&self, k: usize) -> f64 {
+        self.covariance[k][k].sqrt()
+    }
+}
+
+/// Fits `y_i ≈ Σ_k β_k rows[i][k]`, each point weighted by `1/σ_i²`
+pub fn least_squares(rows: &[Vec<f64>], y: &[f64], sigma: &[f64]) -> Result<Fit> {
+    let terms = rows.first().map_or(0, Vec::len);
+    if rows.len() < terms || terms == 0 {
+        return Err(anyhow!(
+            "{} points can't determine {terms} coefficients",
+            rows.len()
+        ));
+    }
+
+    // Normal equations `XᵀWX β = XᵀWy`
+    let mut normal = vec![vec![0.0; terms]; terms];
+    let mut rhs = vec![0.0; terms];
+    for ((row, &y), &sigma) in rows.iter().zip(y).zip(sigma) {
+        let weight = 1.0 / (sigma * sigma);
+        for j in 0..terms {
+            rhs[j] += weight * row[j] * y;
+            for k in 0..terms {
+                normal[j][k] += weight * row[j] * row[k];
+            }
+        }
+    }
+
+    let covariance = invert(normal).ok_or_else(|| anyhow!("the fit is degenerate"))?;
+    let coefficients: Vec<f64> = covariance
+        .iter()
+        .map(|row| row.iter().zip(&rhs).map(|(a, b)| a * b).sum())
+        .collect();
+    let chi2 = rows
+        .iter()
+        .zip(y)
+        .zip(sigma)
+        .map(|((row, &y), &sigma)| {
+            let fitted: f64 = row.iter().zip(&coefficients).map(|(x, b)| x * b).sum();
+            ((y - fitted) / sigma).powi(2)
+        })
+        .sum();
+
+    Ok(Fit {
+        coefficients,
+        covariance,
+        chi2,
+        dof: rows.len() - terms,
+    })
+}
+
+/// Gauss-Jordan elimination with partial pivoting, `None` if singular
+fn invert(mut matrix: Vec<Vec<f64>>) -> Option<Vec<Vec<f64>>> {
+    let n = matrix.len();
+    let mut inverse: Vec<Vec<f64>> = (0..n)
+        .map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect();
+
+    for column in 0..n {
+        let pivot = (column..n)
+            .max_by(|&a, &b| matrix[a][column].abs().total_cmp(&matrix[b][column].abs()))?;
+        if matrix[pivot][column] == 0.0 || !matrix[pivot][column].is_finite() {
+            return None;
+        }
+        matrix.swap(column, pivot);
+        inverse.swap(column, pivot);
+
+        let scale = matrix[column][column];
+        for k in 0..n {
+            matrix[column][k] /= scale;
+            inverse[column][k] /= scale;
+        }
+        for row in (0..n).filter(|&row| row != column) {
+            let factor = matrix[row][column];
+            for k in 0..n {
+                matrix[row][k] -= factor * matrix[column][k];
+                inverse[row][k] -= factor * inverse[column][k];
+            }
+        }
+    }
+
+    Some(inverse)
+}
+
+/// Finite-size form of a functional, `n^exponent · Σ_k β_k n^{-k·step}`:
+/// for `Σ|e|^α` in dimension `d`, `n^{(d-α)/d}` with corrections in powers
+/// of `n^{-1/d}`, and on the complete graph `n^{1-α}` with corrections in
+/// powers of `1/n`. The leading `β_0` is the limiting constant, `ζ(3)` for
+/// the total weight of the complete graph.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Scaling {
+    pub exponent: f64,
+    pub step: f64,
+}
+
+impl Scaling {
+    /// The form of the functional labelled `functional` by the runner, if
+    /// one is known
+    pub fn of(dimension: u32, functional: &str) -> Option<Self> {
+        let alpha: f64 = functional.strip_prefix("alpha=")?.parse().ok()?;
+        Some(match dimension {
+            0 => Self {
+                exponent: 1.0 - alpha,
+                step: 1.0,
+            },
+            d => {
+                let d = d as f64;
+                Self {
+                    exponent: (d - alpha) / d,
+                    step: 1.0 / d,
+                }
+            }
+        })
+    }
+
+    /// Fits the constant and `corrections` correction terms to the mean of
+    /// the functional at several sizes
+    pub fn fit(&self, sizes: &[(u32, Accumulator)], corrections: usize) -> Result<Fit> {
+        if let Some((points, _)) = sizes.iter().find(|(_, sample)| sample.error() <= 0.0) {
+            return Err(anyhow!(
+                "the mean at {points} points has no error, it needs two trials or more"
+            ));
+        }
+
+        let mut rows = Vec::new();
+        let (mut y, mut sigma) = (Vec::new(), Vec::new());
+        for (points, sample) in sizes {
+            let n = *points as f64;
+            let scale = n.powf(self.exponent);
+            rows.push(
+                (0..=corrections)
+                    .map(|k| n.powf(-(k as f64) * self.step))
+                    .collect(),
+            );
+            y.push(sample.mean() / scale);
+            sigma.push(sample.error() / scale);
+        }
+
+        least_squares(&rows, &y, &sigma)
+    }
+}
+
+/// The configurations of `store` with their sizes, in order of appearance.
+/// The keys of the groups have `points` set to zero.
+pub fn groups(store: &Store) -> Vec<(Key, Vec<(u32, Accumulator)>)> {
+    let mut groups: Vec<(Key, Vec<(u32, Accumulator)>)> = Vec::new();
+    for entry in &store.entries {
+        let key = Key {
+            points: 0,
+            ..entry.key.clone()
+        };
+        let size = (entry.key.points, entry.accumulator);
+        match groups.iter_mut().find(|(group, _)| *group == key) {
+            Some((_, sizes)) => sizes.push(size),
+            None => groups.push((key, vec![size])),
+        }
+    }
+
+    for (_, sizes) in &mut groups {
+        sizes.sort_unstable_by_key(|(points, _)| *points);
+    }
+    groups
+}
+
+/// Reads the lines `mean ± error points trials dimension [functional]`
+/// printed by `sample` and `sweep` with `--error`, as uniform points of
+/// fixed size. What follows in parentheses is ignored.
+pub fn parse_report(text: &str) -> Result<Store> {
+    let mut store = Store::default();
+    for (i, line) in text.lines().enumerate() {
+        let line = strip_colors(line);
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (key, sample) =
+            parse_line(&line).with_context(|| format!("line {}: `{}`", i + 1, line.trim()))?;
+        store.add("report", key, &[], &sample)?;
+    }
+
+    Ok(store)
+}
+
+fn parse_line(line: &str) -> Result<(Key, Accumulator)> {
+    let line = line.split_once(" (").map_or(line, |(fields, _)| fields);
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let [mean, "±", error, points, trials, dimension, rest @ ..] = &fields[..] else {
+        return Err(anyhow!(
+            "expected `mean ± error points trials dimension`, run with --error"
+        ));
+    };
+    let functional = match rest {
+        [] => "alpha=1",
+        [functional] => functional,
+        _ => return Err(anyhow!("unexpected fields after the dimension")),
+    };
+
+    let key = Key {
+        dimension: dimension.parse()?,
+        points: points.parse()?,
+        functional: functional.to_string(),
+        distribution: PointProcess::Uniform,
+        torus: false,
+        poisson: false,
+    };
+    let sample = Accumulator::from_error(trials.parse()?, mean.parse()?, error.parse()?);
+    Ok((key, sample))
+}
+
+/// Drops the escape sequences coloring the output in a terminal
+fn strip_colors(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|&c| c == 'm');
+        } else {
+            out.push(c);
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{groups, parse_report, Scaling};
+    use crate::statistics::Accumulator;
+
+    #[test]
+    fn exact_forms() {
+        // The plane: 0.7·√n·(1 + 0.3/√n - 0.2/n)
+        let plane = Scaling::of(2, "alpha=1").unwrap();
+        let sizes: Vec<(u32, Accumulator)> = [100, 400, 1600, 6400, 25600]
+            .into_iter()
+            .map(|points| {
+                let n = points as f64;
+                let mean = 0.7 * n.sqrt() * (1.0 + 0.3 / n.sqrt() - 0.2 / n);
+                (points, Accumulator::from_error(100, mean, 0.01))
+            })
+            .collect();
+        let fit = plane.fit(&sizes, 2).unwrap();
+        assert!((fit.coefficients[0] - 0.7).abs() < 1e-9);
+        assert!((fit.coefficients[1] / fit.coefficients[0] - 0.3).abs() < 1e-9);
+        assert!(fit.chi2 < 1e-12 && fit.dof == 2);
+
+        // The complete graph: ζ(3) - 0.5/n, with errors scaled accordingly
+        let complete = Scaling::of(0, "alpha=1").unwrap();
+        let sizes: Vec<(u32, Accumulator)> = [10, 30, 100, 300]
+            .into_iter()
+            .map(|points| {
+                (
+                    points,
+                    Accumulator::from_error(50, 1.202 - 0.5 / points as f64, 1e-3),
+                )
+            })
+            .collect();
+        let fit = complete.fit(&sizes, 1).unwrap();
+        assert!((fit.coefficients[0] - 1.202).abs() < 1e-9);
+        assert!((fit.coefficients[1] + 0.5).abs() < 1e-9);
+        assert!(fit.error(0) > 0.0 && fit.error(0) < 1e-3);
+
+        assert!(Scaling::of(2, "max").is_none());
+        assert!(complete.fit(&sizes[..1], 1).is_err());
+    }
+
+    #[test]
+    fn report() {
+        let text = "\x1b[32m1.198000\x1b[0m ± \x1b[31m0.001000\x1b[0m 100 40 0\n\
+                    # partial: interrupted after 40 of 50 trials\n\
+                    0.654321 ± 0.000100 1000 20 2 alpha=2\n\
+                    1.200000 ± 0.000500 300 10 0 (-4.1σ from ζ(3))\n";
+        let store = parse_report(text).unwrap();
+        let groups = groups(&store);
+        assert_eq!(groups.len(), 2);
+
+        let (key, sizes) = &groups[0];
+        assert_eq!((key.dimension, key.functional.as_str()), (0, "alpha=1"));
+        assert_eq!(sizes.len(), 2);
+        assert_eq!(sizes[0].1.count, 40);
+        assert!((sizes[0].1.error() - 0.001).abs() < 1e-12);
+        assert_eq!(groups[1].0.functional, "alpha=2");
+
+        assert!(parse_report("1.2 100 10 0").is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use crate::{complete, euclidean, runner, statistics::Accumulator};
+
+/// Shortest batch of iterations timed as one sample, so that the clock's
+/// resolution doesn't matter
+const BATCH: Duration = Duration::from_millis(10);
+
+/// Times kernels on stable Rust, much like `cargo bench`: each kernel is
+/// warmed up while finding how many iterations make a batch last `BATCH`,
+/// then timed over `samples` such batches. The rows are printed as they
+/// come.
+pub struct Bench {
+    /// Only the kernels whose name contains it are timed
+    pub filter: Option<String>,
+    pub warmup: Duration,
+    pub samples: u32,
+}
+
+/// Time per iteration of a kernel
+pub struct Timing {
+    pub name: String,
+    /// Seconds per iteration, one value per sample
+    pub time: Accumulator,
+    pub iterations: u64,
+}
+
+impl Bench {
+    pub fn header() {
+        println!(
+            "{:<32} {:>12} {:>10} {:>16}",
+            "kernel", "time", "error", "iterations"
+        );
+    }
+
+    /// Times `kernel` and prints its row, unless filtered out or interrupted
+    pub fn run<T>(&self, name: &str, mut kernel: impl FnMut() -> T) -> Option<Timing> {
+        let filtered = self
+            .filter
+            .as_ref()
+            .is_some_and(|filter| !name.contains(filter.as_str()));
+        if filtered || runner::interrupted() {
+            return None;
+        }
+
+        let mut iterations = 1;
+        let start = Instant::now();
+        loop {
+            let elapsed = batch(&mut kernel, iterations);
+            if elapsed < BATCH {
+                iterations *= 2;
+            } else if start.elapsed() >= self.warmup {
+                break;
+            }
+        }
+
+        let time = (0..self.samples)
+            .map(|_| batch(&mut kernel, iterations).as_secs_f64() / iterations as f64)
+            .collect();
+        let timing = Timing {
+            name: name.to_string(),
+            time,
+            iterations,
+        };
+        timing.print();
+        Some(timing)
+    }
+}
+
+impl Timing {
+    fn print(&self) {
+        println!(
+            "{:<32} {:>12} {:>10} {:>16}",
+            self.name,
+            format_seconds(self.time.mean()),
+            format_seconds(self.time.error()),
+            format!("{}×{}", self.time.count, self.iterations)
+        );
+    }
+}
+
+/// With the unit of `Duration`'s debug output, but below the nanosecond too
+fn format_seconds(seconds: f64) -> String {
+    match seconds {
+        s if s >= 1.0 => format!("{s:.2}s"),
+        s if s >= 1e-3 => format!("{:.2}ms", s * 1e3),
+        s if s >= 1e-6 => format!("{:.2}µs", s * 1e6),
+        s => format!("{:.2}ns", s * 1e9),
+    }
+}
+
+fn batch<T>(kernel: &mut impl FnMut() -> T, iterations: u64) -> Duration {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        black_box(kernel());
+    }
+    start.elapsed()
+}
+
+/// Every kernel, on inputs drawn from fixed seeds so that timings compare
+/// between commits
+pub fn all(bench: &Bench) {
+    complete::kernels(bench);
+    complete::union_find::kernels(bench);
+    euclidean::kernels(bench);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{hint::black_box, time::Duration};
+
+    use super::{format_seconds, Bench, BATCH};
+
+    #[test]
+    fn timing() {
+        let bench = Bench {
+            filter: Some("sum".to_string()),
+            warmup: Duration::ZERO,
+            samples: 3,
+        };
+        let timing = bench
+            .run("sum", || (0..black_box(100)).sum::<u64>())
+            .unwrap();
+        assert_eq!(timing.time.count, 3);
+        // Enough iterations for a batch to last `BATCH`
+        assert!(timing.time.mean() * timing.iterations as f64 > BATCH.as_secs_f64() / 2.0);
+        assert!(bench.run("product", || 1).is_none());
+
+        assert_eq!(format_seconds(2.5), "2.50s");
+        assert_eq!(format_seconds(1.5e-5), "15.00µs");
+        assert_eq!(format_seconds(3.25e-9), "3.25ns");
+    }
+}
@@ -1,11 +1,20 @@
-use rand::RngCore;
+use rand::{rngs::SmallRng, RngCore, SeedableRng};
 
-use self::sampler::FatComponentSampler;
+pub use self::sampler::Workspace;
+use self::{sampler::FatComponentSampler, union_find::MAX_SIZE};
+use crate::{
+    bench::Bench,
+    error::{check_size, Result},
+    functional::PowerSums,
+    tree::Edge,
+};
 
+pub mod reference;
 mod sampler;
 pub mod union_find;
 
-pub fn mst(size: u32, rng: impl RngCore) -> f64 {
+pub fn mst(size: u32, rng: impl RngCore) -> Result<f64> {
+    check_size(size as usize, MAX_SIZE as usize)?;
     let mut sampler = FatComponentSampler::new(rng, size);
     let mut total_weight = 0.0;
 
@@ -13,7 +22,134 @@ pub fn mst(size: u32, rng: impl RngCore) -> f64 {
         total_weight += weight;
     }
 
-    total_weight
+    Ok(total_weight)
+}
+
+/// Samples a tree and accumulates `Σw^α` for each of the `alphas`, plus the
+/// heaviest edge. The memory of `workspace` is reused across calls.
+pub fn mst_powers(
+    size: u32,
+    rng: impl RngCore,
+    alphas: &[f64],
+    workspace: &mut Workspace,
+) -> Result<PowerSums> {
+    check_size(size as usize, MAX_SIZE as usize)?;
+    let mut sampler = FatComponentSampler::with_workspace(rng, size, std::mem::take(workspace));
+    let mut sums = PowerSums::new(alphas);
+
+    while let Some(weight) = sampler.sample() {
+        sums.add(weight);
+    }
+
+    *workspace = sampler.into_workspace();
+    Ok(sums)
+}
+
+/// Samples a tree and returns its edges, in the order they were added
+pub fn mst_edges(size: u32, rng: impl RngCore) -> Result<Vec<Edge>> {
+    check_size(size as usize, MAX_SIZE as usize)?;
+    let mut sampler = FatComponentSampler::new(rng, size);
+    let mut edges = Vec::new();
+
+    while let Some((u, v, weight)) = sampler.sample_edge() {
+        edges.push(Edge {
+            u: u.index(),
+            v: v.index(),
+            length: weight,
+        });
+    }
+
+    Ok(edges)
+}
+
+/// The kernels of `randmst bench`
+pub fn kernels(bench: &Bench) {
+    // Speed test from class
+    let mut rng = SmallRng::seed_from_u64(0);
+    bench.run("complete mst 262144", || mst(262_144, &mut rng).unwrap());
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    use super::{
+        mst, mst_edges, mst_powers,
+        reference::{kruskal, uniform_weights},
+        union_find::{Point, SizedUnionFind},
+        Workspace,
+    };
+    use crate::{error::Error, tree::Edge, validate};
+
+    const INSTANCES: u64 = 4000;
+
+    /// Checks that `edges` is a spanning tree added in increasing order
+    fn assert_tree(size: u32, edges: &[Edge], seed: u64) {
+        let mut set = SizedUnionFind::new(size);
+        assert_eq!(edges.len() as u32, size - 1, "seed {seed}");
+
+        let mut previous = 0.0;
+        for edge in edges {
+            assert!(
+                set.unite(Point::new(edge.u), Point::new(edge.v)),
+                "seed {seed}: edge {edge:?} closes a cycle"
+            );
+            assert!(
+                (previous..=1.0).contains(&edge.length),
+                "seed {seed}: edge {edge:?} out of order"
+            );
+            previous = edge.length;
+        }
+    }
+
+    #[test]
+    fn sampled_trees_are_spanning() {
+        for seed in 0..INSTANCES {
+            let size = SmallRng::seed_from_u64(seed).gen_range(1..=200);
+            let edges = mst_edges(size, SmallRng::seed_from_u64(seed)).unwrap();
+            assert_tree(size, &edges, seed);
+
+            let total: f64 = edges.iter().map(|edge| edge.length).sum();
+            assert_eq!(
+                mst(size, SmallRng::seed_from_u64(seed)),
+                Ok(total),
+                "seed {seed}"
+            );
+        }
+    }
+
+    #[test]
+    fn reused_workspace() {
+        let mut workspace = Workspace::default();
+        for (seed, size) in [(0, 500), (1, 20), (2, 2000), (3, 1)] {
+            let sums = mst_powers(size, SmallRng::seed_from_u64(seed), &[1.0], &mut workspace);
+            let total = mst(size, SmallRng::seed_from_u64(seed)).unwrap();
+            assert_eq!(sums.unwrap().sums()[0], total);
+        }
+    }
+
+    #[test]
+    fn invalid_sizes() {
+        let rng = || SmallRng::seed_from_u64(0);
+        assert_eq!(mst(0, rng()), Err(Error::NoPoints));
+        assert!(matches!(
+            mst_edges(u32::MAX, rng()),
+            Err(Error::TooManyPoints { .. })
+        ));
+    }
+
+    #[test]
+    fn matches_explicit_weights() {
+        const SIZE: u32 = 20;
+
+        for seed in 0..10 {
+            let edges = kruskal(SIZE, uniform_weights(SIZE, SmallRng::seed_from_u64(seed)));
+            assert_tree(SIZE, &edges, seed);
+        }
+
+        let check = validate::complete(INSTANCES, SIZE, 0);
+        assert_eq!(check.failure, None);
+    }
 }
 
 #[cfg(all(test, feature = "benchmark"))]
@@ -26,6 +162,6 @@ mod benchmarks {
     /// Speed test from class
     #[bench]
     fn main(b: &mut Bencher) {
-        b.iter(|| black_box(mst(262_144, thread_rng())));
+        b.iter(|| black_box(mst(262_144, thread_rng()).unwrap()));
     }
 }
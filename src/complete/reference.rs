@@ -0,0 +1,30 @@
+use rand::{Rng, RngCore};
+
+use super::union_find::{Point, SizedUnionFind};
+use crate::tree::Edge;
+
+/// Every edge of the complete graph on `size` vertices, with independent
+/// `Uniform(0, 1)` weights
+pub fn uniform_weights(size: u32, mut rng: impl RngCore) -> Vec<Edge> {
+    (0..size)
+        .flat_map(|u| (u + 1..size).map(move |v| (u, v)))
+        .map(|(u, v)| Edge {
+            u,
+            v,
+            length: rng.gen(),
+        })
+        .collect()
+}
+
+/// Kruskal's algorithm on explicit weights, returning the tree edges in
+/// increasing order. The sampler generates the same distribution of trees
+/// without ever drawing the `O(n^2)` weights.
+pub fn kruskal(size: u32, mut edges: Vec<Edge>) -> Vec<Edge> {
+    edges.sort_by(|a, b| a.length.total_cmp(&b.length));
+
+    let mut set = SizedUnionFind::new(size);
+    edges
+        .into_iter()
+        .filter(|edge| set.unite(Point::new(edge.u), Point::new(edge.v)))
+        .collect()
+}
@@ -10,10 +10,27 @@ pub struct FatComponent {
 }
 
 impl FatComponent {
-    fn new(root: Point, size: u32) -> Self {
+    fn new(root: Point, size: u32, mut remainders: Vec<Point>) -> Self {
+        remainders.clear();
         Self {
             root,
             size,
+            remainders,
+        }
+    }
+}
+
+/// Memory of a sampler which can be reused by the next one, so that
+/// repeated trials stop allocating
+pub struct Workspace {
+    set: SizedUnionFind,
+    remainders: Vec<Point>,
+}
+
+impl Default for Workspace {
+    fn default() -> Self {
+        Self {
+            set: SizedUnionFind::new(0),
             remainders: Vec::new(),
         }
     }
@@ -25,20 +42,53 @@ pub struct FatComponentSampler<R: RngCore> {
     total_count: u32,
     set: SizedUnionFind,
     rng: R,
+
+    // Buffer for the remainders until a fat component is found
+    remainders: Vec<Point>,
 }
 
 impl<R: RngCore> FatComponentSampler<R> {
     pub fn new(rng: R, size: u32) -> Self {
+        Self::with_workspace(rng, size, Workspace::default())
+    }
+
+    /// A sampler using the memory of a previous one
+    pub fn with_workspace(rng: R, size: u32, workspace: Workspace) -> Self {
+        let Workspace {
+            mut set,
+            remainders,
+        } = workspace;
+        set.reset(size);
+
         Self {
             inv_weight: 1.0,
             rng,
-            set: SizedUnionFind::new(size),
-            total_count: size - 1,
+            set,
+            total_count: size.saturating_sub(1),
             fat_component: None,
+            remainders,
+        }
+    }
+
+    /// Gives back the memory of the sampler for the next trial
+    pub fn into_workspace(self) -> Workspace {
+        let remainders = match self.fat_component {
+            Some(component) => component.remainders,
+            None => self.remainders,
+        };
+
+        Workspace {
+            set: self.set,
+            remainders,
         }
     }
 
     pub fn sample(&mut self) -> Option<f64> {
+        self.sample_edge().map(|(_, _, weight)| weight)
+    }
+
+    /// Samples the next edge of the tree together with its weight
+    pub fn sample_edge(&mut self) -> Option<(Point, Point, f64)> {
         if self.set.free_edges() == 0 || self.total_count == 0 {
             return None;
         }
@@ -59,7 +109,7 @@ impl<R: RngCore> FatComponentSampler<R> {
 
         // If we haven't found a fat component yet, look for it!
         if (self.set.free_edges() * 2 < self.set.total_edges()) & self.fat_component.is_none() {
-            self.fat_component = find_fat_component(&mut self.set);
+            self.fat_component = find_fat_component(&mut self.set, &mut self.remainders);
         }
 
         loop {
@@ -73,17 +123,23 @@ impl<R: RngCore> FatComponentSampler<R> {
             }
 
             self.total_count -= 1;
-            return Some(1.0 - self.inv_weight);
+            return Some((edge.0, edge.1, 1.0 - self.inv_weight));
         }
     }
 }
 
-fn find_fat_component(set: &mut SizedUnionFind) -> Option<FatComponent> {
+/// Looks for a component holding at least half of the points, taking the
+/// `remainders` buffer when it finds one
+fn find_fat_component(
+    set: &mut SizedUnionFind,
+    remainders: &mut Vec<Point>,
+) -> Option<FatComponent> {
     for v in set.iter() {
         if set.size(v) * 2 >= set.total_size() {
             let root = set.root(v);
 
-            let mut fat_component = FatComponent::new(root, set.size(v));
+            let mut fat_component =
+                FatComponent::new(root, set.size(v), std::mem::take(remainders));
             for w in set.iter() {
                 if set.root(w) != root {
                     fat_component.remainders.push(w);
@@ -103,17 +159,11 @@ fn update_fat_component(set: &mut SizedUnionFind, component: &mut FatComponent)
     component.size = set.size(component.root);
 
     if (set.total_size() - component.size) * 2 < component.remainders.len() as u32 {
-        // Marginally faster than retain (filter rate is too low for retain to be effective)
-        component.remainders = component
+        // In place, so that the buffer can be reused by the next trial
+        let root = component.root;
+        component
             .remainders
-            .iter()
-            .copied()
-            .filter(|point| set.root(*point) != component.root)
-            .collect();
-
-        // component
-        //     .remainders
-        //     .retain(|point| set.root(*point) != component.root);
+            .retain(|point| set.root(*point) != root);
     }
 }
 
@@ -180,3 +230,143 @@ fn sample_remainder(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    use super::FatComponentSampler;
+    use crate::{
+        complete::reference::{kruskal, uniform_weights},
+        tree::Edge,
+    };
+
+    const TRIALS: u64 = 4000;
+
+    // Critical values at a significance of 0.001
+    const KS_COEFFICIENT: f64 = 1.949;
+    const CHI_SQUARE_3: f64 = 16.266;
+
+    fn sampled(size: u32, seed: u64) -> Vec<Edge> {
+        let mut sampler = FatComponentSampler::new(SmallRng::seed_from_u64(seed), size);
+        std::iter::from_fn(|| sampler.sample_edge())
+            .map(|(u, v, length)| Edge {
+                u: u.index(),
+                v: v.index(),
+                length,
+            })
+            .collect()
+    }
+
+    fn explicit(size: u32, seed: u64) -> Vec<Edge> {
+        // Keep away from the seeds of the sampler
+        let rng = SmallRng::seed_from_u64(seed + (1 << 32));
+        kruskal(size, uniform_weights(size, rng))
+    }
+
+    /// Largest distance between the empirical distribution functions
+    fn ks_statistic(mut a: Vec<f64>, mut b: Vec<f64>) -> f64 {
+        a.sort_by(f64::total_cmp);
+        b.sort_by(f64::total_cmp);
+
+        let (mut i, mut j, mut statistic) = (0, 0, 0.0_f64);
+        while i < a.len() && j < b.len() {
+            let x = a[i].min(b[j]);
+            while i < a.len() && a[i] <= x {
+                i += 1;
+            }
+            while j < b.len() && b[j] <= x {
+                j += 1;
+            }
+
+            let gap = i as f64 / a.len() as f64 - j as f64 / b.len() as f64;
+            statistic = statistic.max(gap.abs());
+        }
+
+        statistic
+    }
+
+    /// Two sample Kolmogorov-Smirnov test of `statistic` over the trees
+    fn assert_same_distribution(size: u32, name: &str, statistic: impl Fn(&[Edge]) -> f64) {
+        let a: Vec<f64> = (0..TRIALS)
+            .map(|seed| statistic(&sampled(size, seed)))
+            .collect();
+        let b: Vec<f64> = (0..TRIALS)
+            .map(|seed| statistic(&explicit(size, seed)))
+            .collect();
+
+        let critical = KS_COEFFICIENT * (2.0 / TRIALS as f64).sqrt();
+        let distance = ks_statistic(a, b);
+        assert!(
+            distance < critical,
+            "{name} of the {size} vertex tree: KS distance {distance:.4} above {critical:.4}"
+        );
+    }
+
+    /// Chi-square statistic of the homogeneity of two histograms
+    fn chi_square(a: &[u64], b: &[u64]) -> f64 {
+        let (total_a, total_b) = (a.iter().sum::<u64>() as f64, b.iter().sum::<u64>() as f64);
+        let total = total_a + total_b;
+
+        a.iter()
+            .zip(b)
+            .map(|(&x, &y)| {
+                let column = (x + y) as f64;
+                let (expected_a, expected_b) = (column * total_a / total, column * total_b / total);
+                (x as f64 - expected_a).powi(2) / expected_a
+                    + (y as f64 - expected_b).powi(2) / expected_b
+            })
+            .sum()
+    }
+
+    /// Counts of vertex degrees 1, 2, 3 and above over every tree
+    fn degrees(size: u32, trees: impl Iterator<Item = Vec<Edge>>) -> [u64; 4] {
+        let mut histogram = [0; 4];
+        for edges in trees {
+            let mut degree = vec![0_usize; size as usize];
+            for edge in edges {
+                degree[edge.u as usize] += 1;
+                degree[edge.v as usize] += 1;
+            }
+
+            for d in degree {
+                histogram[d.clamp(1, 4) - 1] += 1;
+            }
+        }
+
+        histogram
+    }
+
+    #[test]
+    fn total_weight() {
+        for size in [2, 5, 12, 30] {
+            assert_same_distribution(size, "total weight", |edges| {
+                edges.iter().map(|edge| edge.length).sum()
+            });
+        }
+    }
+
+    #[test]
+    fn order_statistics() {
+        for size in [5, 12, 30] {
+            let middle = size as usize / 2;
+            assert_same_distribution(size, "lightest edge", |edges| edges[0].length);
+            assert_same_distribution(size, "median edge", |edges| edges[middle].length);
+            assert_same_distribution(size, "heaviest edge", |edges| edges.last().unwrap().length);
+        }
+    }
+
+    #[test]
+    fn degree_distribution() {
+        for size in [5, 12, 30] {
+            let a = degrees(size, (0..TRIALS).map(|seed| sampled(size, seed)));
+            let b = degrees(size, (0..TRIALS).map(|seed| explicit(size, seed)));
+
+            let statistic = chi_square(&a, &b);
+            assert!(
+                statistic < CHI_SQUARE_3,
+                "degrees of the {size} vertex tree: chi-square {statistic:.2} for {a:?} against {b:?}"
+            );
+        }
+    }
+}
@@ -3,17 +3,34 @@ use std::{
     ops::{Index, IndexMut},
 };
 
+use rand::{rngs::SmallRng, Rng, SeedableRng};
 use rand_distr::{Distribution, Uniform};
 use smallvec::SmallVec;
 
+use crate::bench::Bench;
+
 #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
 pub struct Point(u32);
 
+impl Point {
+    pub fn new(index: u32) -> Self {
+        Self(index)
+    }
+
+    pub fn index(&self) -> u32 {
+        self.0
+    }
+}
+
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
 pub struct LinkSizeCompact {
     data: Cell<u32>,
 }
 
+/// Largest number of points a set can hold, the top bit of each link is
+/// taken by the root sentinel
+pub const MAX_SIZE: u32 = LinkSizeCompact::SENTINEL - 1;
+
 pub enum LinkSize {
     Parent(Point),
     Size(u32),
@@ -84,18 +101,30 @@ pub struct SizedUnionFind {
 
 impl SizedUnionFind {
     pub fn new(size: u32) -> Self {
-        assert_eq!(size & LinkSizeCompact::SENTINEL, 0);
-        Self {
-            data: (0..size)
-                .into_iter()
-                .map(|_| LinkSizeCompact::root(1))
-                .collect(),
-            size,
-            total_edges: (size as usize) * (size as usize - 1) / 2,
+        let mut set = Self {
+            data: Vec::new(),
+            size: 0,
+            total_edges: 0,
             queue: Vec::new(),
             total_internal: 0,
-            vertex_distr: Uniform::new(0, size),
-        }
+            vertex_distr: Uniform::new(0, 1),
+        };
+        set.reset(size);
+        set
+    }
+
+    /// Makes every point of `0..size` a singleton again, reusing the memory
+    /// of the previous sets
+    pub fn reset(&mut self, size: u32) {
+        assert_eq!(size & LinkSizeCompact::SENTINEL, 0);
+        self.data.clear();
+        self.data
+            .extend((0..size).map(|_| LinkSizeCompact::root(1)));
+        self.size = size;
+        self.total_edges = (size as usize) * (size as usize).saturating_sub(1) / 2;
+        self.total_internal = 0;
+        // Nothing is ever sampled from an empty set
+        self.vertex_distr = Uniform::new(0, size.max(1));
     }
 
     /// Unites the two sets, returns the size and root of the united
@@ -249,6 +278,45 @@ impl Distribution<Point> for SizedUnionFind {
     }
 }
 
+/// The kernels of `randmst bench`, on sets of 100 000 points cycling
+/// through 10%, 50% and 80% of them united at random
+pub fn kernels(bench: &Bench) {
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut sets: Vec<SizedUnionFind> = [0.1, 0.5, 0.8]
+        .into_iter()
+        .map(|ratio| {
+            let mut set = SizedUnionFind::new(100_000);
+            for _ in 0..(100_000.0 * ratio) as usize {
+                set.unite(rng.sample(&set), rng.sample(&set));
+            }
+            set
+        })
+        .collect();
+    let mut i = 0;
+    let mut next = move || {
+        i = (i + 1) % 3;
+        i
+    };
+
+    bench.run("union-find root", || {
+        let set = &sets[next()];
+        set.root(rng.sample(set))
+    });
+    bench.run("union-find same_set", || {
+        let set = &sets[next()];
+        set.same_set(rng.sample(set), rng.sample(set))
+    });
+    bench.run("union-find same_set+unite", || {
+        let set = &mut sets[next()];
+        let (u, v) = (rng.sample(&*set), rng.sample(&*set));
+        !set.same_set(u, v) && set.unite(u, v)
+    });
+    bench.run("union-find unite", || {
+        let set = &mut sets[next()];
+        set.unite(rng.sample(&*set), rng.sample(&*set))
+    });
+}
+
 #[cfg(test)]
 mod tests {
     use super::{Point, SizedUnionFind};
@@ -275,6 +343,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn simple() {
         let mut set = SizedUnionFind::new(10);
 
@@ -293,6 +362,18 @@ mod tests {
         assert_eq!(set.linked_edges(), 15);
         assert_eq!(set.free_edges(), 30);
     }
+
+    #[test]
+    fn reset() {
+        let mut set = SizedUnionFind::new(10);
+        set.unite(Point(1), Point(2));
+        set.unite(Point(2), Point(3));
+
+        set.reset(6);
+        assert!(!set.same_set(Point(1), Point(2)));
+        assert_eq!(set.linked_edges(), 0);
+        assert_eq!(set.free_edges(), 15);
+    }
 }
 
 #[cfg(all(test, feature = "benchmark"))]
@@ -0,0 +1,59 @@
+use std::fmt::Display;
+
+/// Inputs the models can't handle
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// A tree needs at least one point
+    NoPoints,
+    /// More points than the model can index
+    TooManyPoints { size: usize, max: usize },
+    /// No model lives in this dimension
+    Dimension(u32),
+    /// The algorithm only works in the plane
+    PlanarOnly {
+        algorithm: &'static str,
+        dimension: usize,
+    },
+    /// The option only makes sense on the line
+    LineOnly {
+        option: &'static str,
+        dimension: usize,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoPoints => write!(f, "a tree needs at least one point"),
+            Self::TooManyPoints { size, max } => {
+                write!(f, "{size} points is more than the supported {max}")
+            }
+            Self::Dimension(dimension) => write!(f, "dimension {dimension} is not supported"),
+            Self::PlanarOnly {
+                algorithm,
+                dimension,
+            } => write!(
+                f,
+                "the {algorithm} algorithm only applies to the plane, not dimension {dimension}"
+            ),
+            Self::LineOnly { option, dimension } => write!(
+                f,
+                "the {option} option only applies to the line, not dimension {dimension}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Checks that `size` points can be handled by a model indexing them below
+/// `max`
+pub fn check_size(size: usize, max: usize) -> Result<()> {
+    match size {
+        0 => Err(Error::NoPoints),
+        size if size > max => Err(Error::TooManyPoints { size, max }),
+        _ => Ok(()),
+    }
+}
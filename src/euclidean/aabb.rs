@@ -1,7 +1,8 @@
 use std::fmt::Display;
 
-use super::point::Point;
+use super::point::{Point, MANTISSA_BITS};
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Debug)]
 pub struct AABB<const D: usize> {
     min: Point<D>,
@@ -31,12 +32,15 @@ impl<const D: usize> AABB<D> {
         Self { min, max }
     }
 
+    /// Squared distance from the point to the boundary of the box, in
+    /// the same fixed point units as `Point::distance2_fixed`.
     /// Assumes point is within box
     pub fn dist2(&self, point: Point<D>) -> u64 {
         let dmin = point - self.min;
         let dmax = self.max - point;
         let delt = dmin.min(dmax);
-        *delt.coords().iter().min().unwrap()
+        let delt = *delt.coords().iter().min().unwrap() as u128;
+        (delt * delt).wrapping_shr(MANTISSA_BITS) as u64
     }
 
     pub fn point(point: Point<D>) -> Self {
@@ -60,6 +64,26 @@ impl<const D: usize> AABB<D> {
         }
     }
 
+    /// Squared distance between the closest points of two boxes
+    pub fn distance2(&self, other: &Self) -> u64 {
+        let (a, b) = (self.min.coords(), self.max.coords());
+        let (c, d) = (other.min.coords(), other.max.coords());
+
+        let mut sum = 0_u128;
+        for i in 0..D {
+            let gap = c[i].saturating_sub(b[i]).max(a[i].saturating_sub(d[i])) as u128;
+            sum += gap * gap;
+        }
+
+        sum.wrapping_shr(MANTISSA_BITS) as u64
+    }
+
+    /// Index of the axis along which the box is the widest
+    pub fn widest_axis(&self) -> usize {
+        let (min, max) = (self.min.coords(), self.max.coords());
+        (0..D).max_by_key(|&i| max[i] - min[i]).unwrap_or(0)
+    }
+
     pub fn diag2(&self) -> u64 {
         self.min.distance2_fixed(&self.max)
     }
@@ -0,0 +1,284 @@
+use std::ops::Range;
+
+use super::{
+    aabb::AABB,
+    kruskal::{Dist2Edge, Point as Index, UnionFind},
+    point::Point,
+};
+
+// Points per leaf of the kd-tree
+const LEAF_SIZE: usize = 16;
+
+struct Node<const D: usize> {
+    bounds: AABB<D>,
+    range: Range<u32>,
+    children: Option<(u32, u32)>,
+}
+
+/// A kd-tree splitting the widest axis at the median, with the points
+/// stored in the order of its leaves
+struct KdTree<const D: usize> {
+    points: Vec<Point<D>>,
+    // Position of each (reordered) point in the input
+    original: Vec<u32>,
+    nodes: Vec<Node<D>>,
+}
+
+impl<const D: usize> KdTree<D> {
+    fn new(points: &[Point<D>]) -> Self {
+        let mut order: Vec<(Point<D>, u32)> = points.iter().copied().zip(0..).collect();
+
+        let mut nodes = Vec::new();
+        if !order.is_empty() {
+            Self::build(&mut nodes, &mut order, 0);
+        }
+
+        Self {
+            points: order.iter().map(|x| x.0).collect(),
+            original: order.iter().map(|x| x.1).collect(),
+            nodes,
+        }
+    }
+
+    /// Appends the subtree of `points` (starting at `offset`) and returns
+    /// the index of its root
+    fn build(nodes: &mut Vec<Node<D>>, points: &mut [(Point<D>, u32)], offset: u32) -> u32 {
+        let bounds = points
+            .iter()
+            .fold(AABB::default(), |bounds, x| AABB::expand(bounds, x.0));
+        let range = offset..offset + points.len() as u32;
+
+        let index = nodes.len() as u32;
+        nodes.push(Node {
+            bounds,
+            range,
+            children: None,
+        });
+
+        if points.len() > LEAF_SIZE {
+            let axis = nodes[index as usize].bounds.widest_axis();
+            let mid = points.len() / 2;
+            points.select_nth_unstable_by_key(mid, |x| x.0.coords()[axis]);
+
+            let (first, second) = points.split_at_mut(mid);
+            let left = Self::build(nodes, first, offset);
+            let right = Self::build(nodes, second, offset + mid as u32);
+            nodes[index as usize].children = Some((left, right));
+        }
+
+        index
+    }
+}
+
+// Candidate edge of a component, ordered by length and then by endpoints
+// so that every round picks edges of a single minimum spanning tree
+type Candidate = (u64, u32, u32);
+
+const NONE: Candidate = (u64::MAX, u32::MAX, u32::MAX);
+
+/// State of the dual-tree traversal during one Borůvka round
+struct Boruvka<'a, const D: usize> {
+    tree: &'a KdTree<D>,
+    // Component (root in the union-find) of each point
+    component: Vec<u32>,
+    // Component shared by every point below a node, if any
+    node_component: Vec<Option<u32>>,
+    // Upper bound on the candidate length of the points below a node
+    node_bound: Vec<u64>,
+    // Shortest edge leaving each component, indexed by its root
+    best: Vec<Candidate>,
+}
+
+impl<'a, const D: usize> Boruvka<'a, D> {
+    fn new(tree: &'a KdTree<D>) -> Self {
+        let size = tree.points.len();
+        Self {
+            tree,
+            component: (0..size as u32).collect(),
+            node_component: vec![None; tree.nodes.len()],
+            node_bound: vec![u64::MAX; tree.nodes.len()],
+            best: vec![NONE; size],
+        }
+    }
+
+    /// Recomputes the components of the points and nodes, returning
+    /// whether more than one remains
+    fn update(&mut self, union: &UnionFind) -> bool {
+        for (i, component) in self.component.iter_mut().enumerate() {
+            *component = union.root(Index(i as u32)).0;
+        }
+
+        // Children always come after their parent
+        for node in (0..self.tree.nodes.len()).rev() {
+            self.node_component[node] = match self.tree.nodes[node].children {
+                Some((left, right)) => {
+                    let left = self.node_component[left as usize];
+                    left.filter(|_| left == self.node_component[right as usize])
+                }
+                None => {
+                    let range = self.tree.nodes[node].range.clone();
+                    let first = self.component[range.start as usize];
+                    range
+                        .map(|i| self.component[i as usize])
+                        .all(|x| x == first)
+                        .then_some(first)
+                }
+            };
+        }
+
+        self.node_component.first() == Some(&None)
+    }
+
+    /// Finds the shortest edge leaving every component
+    fn round(&mut self) {
+        self.node_bound.fill(u64::MAX);
+        self.best.fill(NONE);
+        self.traverse(0, 0);
+    }
+
+    fn traverse(&mut self, query: u32, reference: u32) {
+        let (q, r) = (query as usize, reference as usize);
+        let nodes = &self.tree.nodes;
+
+        if self.node_component[q].is_some() && self.node_component[q] == self.node_component[r] {
+            return;
+        }
+
+        if nodes[q].bounds.distance2(&nodes[r].bounds) > self.node_bound[q] {
+            return;
+        }
+
+        match (nodes[q].children, nodes[r].children) {
+            (None, None) => self.base_case(q, r),
+            (None, Some(children)) => {
+                let (near, far) = self.order(q, children);
+                self.traverse(query, near);
+                self.traverse(query, far);
+            }
+            (Some((left, right)), None) => {
+                self.traverse(left, reference);
+                self.traverse(right, reference);
+                self.bound_from_children(q, left, right);
+            }
+            (Some((left, right)), Some(children)) => {
+                for child in [left, right] {
+                    let (near, far) = self.order(child as usize, children);
+                    self.traverse(child, near);
+                    self.traverse(child, far);
+                }
+                self.bound_from_children(q, left, right);
+            }
+        }
+    }
+
+    fn base_case(&mut self, q: usize, r: usize) {
+        let tree = self.tree;
+        let mut bound = 0;
+
+        for i in tree.nodes[q].range.clone() {
+            let component = self.component[i as usize];
+            let point = &tree.points[i as usize];
+            let mut best = self.best[component as usize];
+
+            for j in tree.nodes[r].range.clone() {
+                if self.component[j as usize] != component {
+                    let dist2 = point.distance2_fixed(&tree.points[j as usize]);
+                    best = best.min((dist2, i.min(j), i.max(j)));
+                }
+            }
+
+            self.best[component as usize] = best;
+            bound = bound.max(best.0);
+        }
+
+        self.node_bound[q] = self.node_bound[q].min(bound);
+    }
+
+    fn bound_from_children(&mut self, q: usize, left: u32, right: u32) {
+        let bound = self.node_bound[left as usize].max(self.node_bound[right as usize]);
+        self.node_bound[q] = self.node_bound[q].min(bound);
+    }
+
+    /// The two reference children, closest to the query node first
+    fn order(&self, q: usize, (left, right): (u32, u32)) -> (u32, u32) {
+        let nodes = &self.tree.nodes;
+        let distance = |x: u32| nodes[q].bounds.distance2(&nodes[x as usize].bounds);
+        match distance(left) <= distance(right) {
+            true => (left, right),
+            false => (right, left),
+        }
+    }
+}
+
+/// Computes the minimum spanning tree with dual-tree Borůvka on a kd-tree
+/// (March, Ram and Gray, 2010). Each round finds the shortest edge leaving
+/// every component in a single traversal of the tree against itself,
+/// pruning pairs of nodes which are within one component or further apart
+/// than the best candidates found so far.
+pub fn boruvka<const D: usize>(points: &[Point<D>]) -> Vec<Dist2Edge> {
+    let tree = KdTree::new(points);
+    let union = UnionFind::new(points.len() as u32);
+    let mut state = Boruvka::new(&tree);
+    let mut mst = Vec::with_capacity(points.len().saturating_sub(1));
+
+    while state.update(&union) {
+        state.round();
+
+        for (root, &(dist2, u, v)) in state.best.iter().enumerate() {
+            if state.component[root] == root as u32
+                && (dist2, u, v) != NONE
+                && union.unite(Index(u), Index(v))
+            {
+                mst.push(Dist2Edge {
+                    u: tree.original[u as usize],
+                    v: tree.original[v as usize],
+                    dist2,
+                });
+            }
+        }
+    }
+
+    mst
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    use super::boruvka;
+    use crate::euclidean::{
+        kruskal::Strategy,
+        point::{Hypercube, Point},
+        spatial::SpatialVec,
+    };
+
+    #[test]
+    fn matches_grid() {
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        for size in [200, 1000, 5000] {
+            let points: Vec<Point<3>> = (&mut rng).sample_iter(Hypercube::<3>).take(size).collect();
+            let total = |edges: Vec<_>| -> u128 {
+                edges
+                    .iter()
+                    .map(|edge: &super::Dist2Edge| edge.dist2 as u128)
+                    .sum()
+            };
+
+            let expected =
+                SpatialVec::new(points.iter().copied()).mst(usize::MAX, Strategy::default());
+            let edges = boruvka(&points);
+            assert_eq!(edges.len(), size - 1);
+            assert_eq!(total(edges), total(expected));
+        }
+    }
+
+    #[test]
+    fn tiny() {
+        assert!(boruvka::<2>(&[]).is_empty());
+        assert!(boruvka(&[Point::from([1_u64, 2])]).is_empty());
+
+        let points = [Point::from([0_u64, 0]), Point::from([0, 1 << 50])];
+        assert_eq!(boruvka(&points).len(), 1);
+    }
+}
@@ -0,0 +1,341 @@
+use super::{
+    kruskal::{kruskal, Dist2Edge, Strategy, UnionFind},
+    morton::Morton,
+    point::{Point, MANTISSA_BITS},
+};
+
+/// Signed 256 bit integer in two's complement, just wide enough for the
+/// incircle determinant
+#[derive(Clone, Copy)]
+struct Wide {
+    hi: u128,
+    lo: u128,
+}
+
+impl Wide {
+    const ZERO: Self = Self { hi: 0, lo: 0 };
+
+    fn product(a: i128, b: i128) -> Self {
+        const HALF: u32 = 64;
+        let (x, y) = (a.unsigned_abs(), b.unsigned_abs());
+        let (x1, x0) = (x >> HALF, x & u64::MAX as u128);
+        let (y1, y0) = (y >> HALF, y & u64::MAX as u128);
+
+        let (mid, carry) = (x0 * y1).overflowing_add(x1 * y0);
+        let (lo, low_carry) = (x0 * y0).overflowing_add(mid << HALF);
+        let hi = x1 * y1 + ((carry as u128) << HALF) + (mid >> HALF) + low_carry as u128;
+
+        let product = Self { hi, lo };
+        match (a < 0) != (b < 0) {
+            true => product.neg(),
+            false => product,
+        }
+    }
+
+    fn add(self, other: Self) -> Self {
+        let (lo, carry) = self.lo.overflowing_add(other.lo);
+        Self {
+            hi: self.hi.wrapping_add(other.hi).wrapping_add(carry as u128),
+            lo,
+        }
+    }
+
+    fn neg(self) -> Self {
+        Self {
+            hi: !self.hi,
+            lo: !self.lo,
+        }
+        .add(Self { hi: 0, lo: 1 })
+    }
+
+    fn is_positive(&self) -> bool {
+        self.hi >> 127 == 0 && (self.hi | self.lo) != 0
+    }
+}
+
+type Coords = [i64; 2];
+
+/// Twice the signed area of `a, b, c`, positive when counterclockwise
+fn orient(a: Coords, b: Coords, c: Coords) -> i128 {
+    let (abx, aby) = (b[0] as i128 - a[0] as i128, b[1] as i128 - a[1] as i128);
+    let (acx, acy) = (c[0] as i128 - a[0] as i128, c[1] as i128 - a[1] as i128);
+    abx * acy - aby * acx
+}
+
+/// Whether `d` is strictly inside the circle through the counterclockwise
+/// triangle `a, b, c`. Exact, the coordinates stay below `2^55` in
+/// magnitude so every term fits comfortably in 256 bits.
+fn in_circle(a: Coords, b: Coords, c: Coords, d: Coords) -> bool {
+    let delta = |p: Coords| (p[0] as i128 - d[0] as i128, p[1] as i128 - d[1] as i128);
+    let ((ax, ay), (bx, by), (cx, cy)) = (delta(a), delta(b), delta(c));
+
+    let terms = [
+        (ax * ax + ay * ay, bx * cy - cx * by),
+        (bx * bx + by * by, cx * ay - ax * cy),
+        (cx * cx + cy * cy, ax * by - bx * ay),
+    ];
+
+    terms
+        .iter()
+        .fold(Wide::ZERO, |sum, &(lift, cross)| {
+            sum.add(Wide::product(lift, cross))
+        })
+        .is_positive()
+}
+
+const NONE: u32 = u32::MAX;
+
+/// A triangulation built by Bowyer-Watson insertion inside a super
+/// triangle. Neighbor `i` of a triangle lies across the edge opposite of
+/// its vertex `i`, and vertices are stored counterclockwise.
+struct Triangulation {
+    coords: Vec<Coords>,
+    vertices: Vec<[u32; 3]>,
+    neighbors: Vec<[u32; 3]>,
+    alive: Vec<bool>,
+    free: Vec<u32>,
+    // Last insertion whose cavity contained the triangle
+    visited: Vec<u32>,
+    // Where the next point location starts
+    last: u32,
+}
+
+impl Triangulation {
+    /// The super triangle contains `[0, 2^51)^2` and its vertices lie
+    /// outside of the disk circumscribing that square. So the diametral
+    /// disk of any two points stays empty of them, which keeps every
+    /// Gabriel edge (and so every tree edge) of the points in the
+    /// triangulation.
+    fn new(mut coords: Vec<Coords>) -> Self {
+        let (near, far) = (
+            -(1_i64 << (MANTISSA_BITS + 1)),
+            1_i64 << (MANTISSA_BITS + 3),
+        );
+        let first = coords.len() as u32;
+        coords.extend([[near, near], [far, near], [near, far]]);
+
+        Self {
+            coords,
+            vertices: vec![[first, first + 1, first + 2]],
+            neighbors: vec![[NONE; 3]],
+            alive: vec![true],
+            free: Vec::new(),
+            visited: vec![NONE],
+            last: 0,
+        }
+    }
+
+    fn edge(&self, triangle: u32, i: usize) -> (u32, u32) {
+        let vertices = self.vertices[triangle as usize];
+        (vertices[(i + 1) % 3], vertices[(i + 2) % 3])
+    }
+
+    /// Visibility walk from the last created triangle, which terminates on
+    /// Delaunay triangulations
+    fn locate(&self, point: Coords) -> u32 {
+        let mut triangle = self.last;
+        'walk: loop {
+            for i in 0..3 {
+                let (u, v) = self.edge(triangle, i);
+                if orient(self.coords[u as usize], self.coords[v as usize], point) < 0 {
+                    triangle = self.neighbors[triangle as usize][i];
+                    continue 'walk;
+                }
+            }
+
+            return triangle;
+        }
+    }
+
+    fn allocate(&mut self, vertices: [u32; 3], neighbors: [u32; 3]) -> u32 {
+        match self.free.pop() {
+            Some(triangle) => {
+                let t = triangle as usize;
+                (self.vertices[t], self.neighbors[t], self.alive[t]) = (vertices, neighbors, true);
+                triangle
+            }
+            None => {
+                self.vertices.push(vertices);
+                self.neighbors.push(neighbors);
+                self.alive.push(true);
+                self.visited.push(NONE);
+                self.vertices.len() as u32 - 1
+            }
+        }
+    }
+
+    fn insert(&mut self, point: u32) {
+        let coords = self.coords[point as usize];
+        let start = self.locate(coords);
+
+        // Flood the triangles whose circumcircle contains the point
+        let mut stack = vec![start];
+        let mut boundary = Vec::new();
+        self.visited[start as usize] = point;
+        while let Some(triangle) = stack.pop() {
+            self.alive[triangle as usize] = false;
+            self.free.push(triangle);
+
+            for i in 0..3 {
+                let neighbor = self.neighbors[triangle as usize][i];
+                if neighbor != NONE && self.visited[neighbor as usize] == point {
+                    continue;
+                }
+
+                let contains = neighbor != NONE && {
+                    let [a, b, c] =
+                        self.vertices[neighbor as usize].map(|x| self.coords[x as usize]);
+                    in_circle(a, b, c, coords)
+                };
+
+                if contains {
+                    self.visited[neighbor as usize] = point;
+                    stack.push(neighbor);
+                } else {
+                    let (u, v) = self.edge(triangle, i);
+                    boundary.push((u, v, neighbor));
+                }
+            }
+        }
+
+        // Fan the boundary of the cavity around the point
+        let mut fan = Vec::with_capacity(boundary.len());
+        for &(u, v, outer) in &boundary {
+            let triangle = self.allocate([u, v, point], [NONE, NONE, outer]);
+            if outer != NONE {
+                let i = self.vertices[outer as usize]
+                    .iter()
+                    .position(|&x| x != u && x != v)
+                    .unwrap();
+                self.neighbors[outer as usize][i] = triangle;
+            }
+            fan.push((u, triangle));
+        }
+
+        for &(_, triangle) in &fan {
+            let v = self.vertices[triangle as usize][1];
+            let next = fan.iter().find(|x| x.0 == v).unwrap().1;
+            self.neighbors[triangle as usize][0] = next;
+            self.neighbors[next as usize][1] = triangle;
+        }
+
+        self.last = fan[0].1;
+    }
+}
+
+/// Computes the minimum spanning tree of planar points by running Kruskal
+/// on the edges of their Delaunay triangulation, which contains it. The
+/// predicates are exact on the fixed point coordinates, so this is an
+/// independent reference for the grid algorithm.
+pub fn delaunay<const D: usize>(points: &[Point<D>], strategy: Strategy) -> Vec<Dist2Edge>
+where
+    Point<D>: Morton,
+{
+    assert_eq!(D, 2, "Delaunay triangulations are planar");
+
+    let coords = points
+        .iter()
+        .map(|p| p.coords().map(|x| x as i64)[..2].try_into().unwrap())
+        .collect();
+    let mut triangulation = Triangulation::new(coords);
+
+    // Insert along the Morton curve to keep the walks short, duplicates end
+    // up next to each other and are joined directly
+    let mut order: Vec<u32> = (0..points.len() as u32).collect();
+    order.sort_by_key(|&i| {
+        (
+            points[i as usize].morton_encode(16),
+            points[i as usize].coords(),
+        )
+    });
+
+    let mut edges = Vec::new();
+    for (k, &i) in order.iter().enumerate() {
+        match k.checked_sub(1).map(|k| order[k]) {
+            Some(j) if points[j as usize].coords() == points[i as usize].coords() => {
+                edges.push(Dist2Edge {
+                    u: j,
+                    v: i,
+                    dist2: 0,
+                })
+            }
+            _ => triangulation.insert(i),
+        }
+    }
+
+    // Every edge appears once in each direction
+    let size = points.len() as u32;
+    for triangle in 0..triangulation.vertices.len() as u32 {
+        if !triangulation.alive[triangle as usize] {
+            continue;
+        }
+
+        for i in 0..3 {
+            let (u, v) = triangulation.edge(triangle, i);
+            if u < v && v < size {
+                let dist2 = points[u as usize].distance2_fixed(&points[v as usize]);
+                edges.push(Dist2Edge { u, v, dist2 });
+            }
+        }
+    }
+
+    kruskal(&mut edges, &UnionFind::new(size), strategy, |_| true)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    use super::{delaunay, in_circle, orient};
+    use crate::euclidean::{
+        boruvka::boruvka,
+        kruskal::{Dist2Edge, Strategy},
+        point::{Hypercube, Point},
+        spatial::SpatialVec,
+    };
+
+    fn total(edges: &[Dist2Edge]) -> u128 {
+        edges.iter().map(|edge| edge.dist2 as u128).sum()
+    }
+
+    #[test]
+    fn predicates() {
+        let far = 1 << 54;
+        assert!(orient([0, 0], [far, 0], [0, far]) > 0);
+        assert!(orient([0, 0], [far, 0], [2 * far, 0]) == 0);
+
+        // Cocircular points are not strictly inside
+        let (a, b, c) = ([-far, -far], [far, -far], [far, far]);
+        assert!(!in_circle(a, b, c, [-far, far]));
+        assert!(in_circle(a, b, c, [-far + 1, far]));
+        assert!(!in_circle(a, b, c, [-far - 1, far]));
+    }
+
+    #[test]
+    fn matches_grid() {
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        for size in [100, 1000, 10000] {
+            let points: Vec<Point<2>> = (&mut rng).sample_iter(Hypercube::<2>).take(size).collect();
+            let expected =
+                SpatialVec::new(points.iter().copied()).mst(usize::MAX, Strategy::default());
+            let edges = delaunay(&points, Strategy::default());
+            assert_eq!(edges.len(), size - 1);
+            assert_eq!(total(&edges), total(&expected));
+        }
+    }
+
+    #[test]
+    fn degenerate() {
+        // A lattice is as cocircular as it gets, with a few duplicates
+        let step = 1 << 46;
+        let mut points: Vec<Point<2>> = (0..400)
+            .map(|i| Point::from([(i % 20) * step, (i / 20) * step]))
+            .collect();
+        points.extend_from_within(..10);
+
+        let edges = delaunay(&points, Strategy::default());
+        assert_eq!(edges.len(), points.len() - 1);
+        assert_eq!(total(&edges), total(&boruvka(&points)));
+    }
+}
@@ -0,0 +1,365 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{anyhow, Result};
+use rand::{Rng, RngCore};
+use rand_distr::{Beta, Distribution, StandardNormal};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::point::{Hypercube, Point};
+
+/// Most cluster parents a process may have
+const MAX_PARENTS: u32 = 1 << 20;
+
+/// Point processes on the unit cube which can drive the Euclidean model.
+/// Anything which would land outside of `[0, 1)^D` is rejected, so every
+/// process is conditioned on the cube.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum PointProcess {
+    /// Uniform on the unit cube
+    #[default]
+    Uniform,
+    /// Normal around the center of the cube
+    Gaussian { sigma: f64 },
+    /// Uniform in the ball inscribed in the cube
+    Ball,
+    /// Uniform on the sphere inscribed in the cube
+    Sphere,
+    /// Independent `Beta(a, b)` coordinates
+    Beta { a: f64, b: f64 },
+    /// Normal offspring around uniformly placed parents
+    Thomas { parents: u32, sigma: f64 },
+    /// Offspring uniform in a ball around uniformly placed parents
+    Matern { parents: u32, radius: f64 },
+}
+
+impl FromStr for PointProcess {
+    type Err = anyhow::Error;
+
+    /// Parses `name[:param,...]`, e.g. `gaussian:0.1` or `thomas:20,0.05`
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, params) = s.split_once(':').unwrap_or((s, ""));
+        let fields: Vec<&str> = params
+            .split(',')
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .collect();
+        let params = fields
+            .iter()
+            .map(|x| x.parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| anyhow!("invalid parameters for distribution `{s}`"))?;
+
+        let expect = |count: usize| match params.len() == count {
+            true => Ok(()),
+            false => Err(anyhow!("distribution `{name}` takes {count} parameters")),
+        };
+        let positive = |x: f64| match x > 0.0 {
+            true => Ok(x),
+            false => Err(anyhow!("parameters of `{name}` must be positive")),
+        };
+        // Each parent is drawn and kept for every trial
+        let parents = || match fields[0].parse::<u32>() {
+            Ok(count @ 1..=MAX_PARENTS) => Ok(count),
+            _ => Err(anyhow!(
+                "the parents of `{name}` must be a whole number from 1 to {MAX_PARENTS}"
+            )),
+        };
+
+        let process = match name {
+            "uniform" => expect(0).map(|_| Self::Uniform),
+            "gaussian" => expect(1).map(|_| Self::Gaussian { sigma: params[0] }),
+            "ball" => expect(0).map(|_| Self::Ball),
+            "sphere" => expect(0).map(|_| Self::Sphere),
+            "beta" => expect(2).map(|_| Self::Beta {
+                a: params[0],
+                b: params[1],
+            }),
+            "thomas" => expect(2).and_then(|_| {
+                Ok(Self::Thomas {
+                    parents: parents()?,
+                    sigma: params[1],
+                })
+            }),
+            "matern" => expect(2).and_then(|_| {
+                Ok(Self::Matern {
+                    parents: parents()?,
+                    radius: params[1],
+                })
+            }),
+            _ => Err(anyhow!("unknown distribution `{name}`")),
+        }?;
+
+        match process {
+            Self::Gaussian { sigma } => positive(sigma).map(|_| process),
+            Self::Beta { a, b } => positive(a.min(b)).map(|_| process),
+            Self::Thomas { sigma: x, .. } | Self::Matern { radius: x, .. } => {
+                positive(x).map(|_| process)
+            }
+            _ => Ok(process),
+        }
+    }
+}
+
+impl Display for PointProcess {
+    /// Writes the process back in the form `from_str` parses
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Uniform => write!(f, "uniform"),
+            Self::Gaussian { sigma } => write!(f, "gaussian:{sigma}"),
+            Self::Ball => write!(f, "ball"),
+            Self::Sphere => write!(f, "sphere"),
+            Self::Beta { a, b } => write!(f, "beta:{a},{b}"),
+            Self::Thomas { parents, sigma } => write!(f, "thomas:{parents},{sigma}"),
+            Self::Matern { parents, radius } => write!(f, "matern:{parents},{radius}"),
+        }
+    }
+}
+
+/// Processes are stored in the same `name[:param,...]` form as on the
+/// command line
+impl Serialize for PointProcess {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PointProcess {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+impl PointProcess {
+    /// Draws whatever randomness is shared by every point of a trial (the
+    /// cluster parents) and returns a distribution of the individual points
+    pub fn sampler<const D: usize>(&self, rng: &mut impl RngCore) -> ProcessSampler<D> {
+        let parents = |rng: &mut dyn RngCore, count: u32| {
+            (0..count)
+                .map(|_| std::array::from_fn(|_| rng.gen::<f64>()))
+                .collect()
+        };
+
+        match *self {
+            Self::Uniform => ProcessSampler::Uniform(Hypercube),
+            Self::Gaussian { sigma } => ProcessSampler::Gaussian(Gaussian { sigma }),
+            Self::Ball => ProcessSampler::Ball(Ball),
+            Self::Sphere => ProcessSampler::Sphere(Sphere),
+            Self::Beta { a, b } => ProcessSampler::Beta(BetaProduct {
+                beta: Beta::new(a, b).unwrap(),
+            }),
+            Self::Thomas {
+                parents: count,
+                sigma,
+            } => ProcessSampler::Thomas(Thomas {
+                parents: parents(rng, count),
+                sigma,
+            }),
+            Self::Matern {
+                parents: count,
+                radius,
+            } => ProcessSampler::Matern(Matern {
+                parents: parents(rng, count),
+                radius,
+            }),
+        }
+    }
+}
+
+/// The per-trial distribution of a `PointProcess`
+pub enum ProcessSampler<const D: usize> {
+    Uniform(Hypercube<D>),
+    Gaussian(Gaussian<D>),
+    Ball(Ball<D>),
+    Sphere(Sphere<D>),
+    Beta(BetaProduct<D>),
+    Thomas(Thomas<D>),
+    Matern(Matern<D>),
+}
+
+impl<const D: usize> Distribution<Point<D>> for ProcessSampler<D>
+where
+    Hypercube<D>: Distribution<Point<D>>,
+{
+    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Point<D> {
+        match self {
+            Self::Uniform(distr) => distr.sample(rng),
+            Self::Gaussian(distr) => distr.sample(rng),
+            Self::Ball(distr) => distr.sample(rng),
+            Self::Sphere(distr) => distr.sample(rng),
+            Self::Beta(distr) => distr.sample(rng),
+            Self::Thomas(distr) => distr.sample(rng),
+            Self::Matern(distr) => distr.sample(rng),
+        }
+    }
+}
+
+/// Retries `sample` until it lands in the unit cube
+fn conditioned<const D: usize>(mut sample: impl FnMut() -> [f64; D]) -> Point<D> {
+    loop {
+        let coords = sample();
+        if coords.iter().all(|x| (0.0..1.0).contains(x)) {
+            return Point::from_float(coords);
+        }
+    }
+}
+
+fn normal<const D: usize, R: Rng + ?Sized>(rng: &mut R) -> [f64; D] {
+    std::array::from_fn(|_| rng.sample(StandardNormal))
+}
+
+/// A uniformly random direction, scaled by `radius`
+fn direction<const D: usize, R: Rng + ?Sized>(rng: &mut R, radius: f64) -> [f64; D] {
+    loop {
+        let x: [f64; D] = normal(rng);
+        let norm = x.iter().map(|x| x * x).sum::<f64>().sqrt();
+        if norm > 0.0 {
+            return x.map(|x| x * radius / norm);
+        }
+    }
+}
+
+/// A uniformly random point of the ball of the given radius around `center`
+fn in_ball<const D: usize, R: Rng + ?Sized>(
+    rng: &mut R,
+    center: [f64; D],
+    radius: f64,
+) -> [f64; D] {
+    let radius = radius * rng.gen::<f64>().powf(1.0 / D as f64);
+    let offset = direction::<D, _>(rng, radius);
+    std::array::from_fn(|i| center[i] + offset[i])
+}
+
+const CENTER: f64 = 0.5;
+
+pub struct Gaussian<const D: usize> {
+    sigma: f64,
+}
+
+impl<const D: usize> Distribution<Point<D>> for Gaussian<D> {
+    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Point<D> {
+        conditioned(|| normal::<D, _>(rng).map(|x| CENTER + self.sigma * x))
+    }
+}
+
+pub struct Ball<const D: usize>;
+
+impl<const D: usize> Distribution<Point<D>> for Ball<D> {
+    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Point<D> {
+        conditioned(|| in_ball(rng, [CENTER; D], CENTER))
+    }
+}
+
+pub struct Sphere<const D: usize>;
+
+impl<const D: usize> Distribution<Point<D>> for Sphere<D> {
+    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Point<D> {
+        conditioned(|| direction::<D, _>(rng, CENTER).map(|x| CENTER + x))
+    }
+}
+
+pub struct BetaProduct<const D: usize> {
+    beta: Beta<f64>,
+}
+
+impl<const D: usize> Distribution<Point<D>> for BetaProduct<D> {
+    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Point<D> {
+        conditioned(|| std::array::from_fn(|_| self.beta.sample(rng)))
+    }
+}
+
+pub struct Thomas<const D: usize> {
+    parents: Vec<[f64; D]>,
+    sigma: f64,
+}
+
+impl<const D: usize> Distribution<Point<D>> for Thomas<D> {
+    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Point<D> {
+        conditioned(|| {
+            let parent = self.parents[rng.gen_range(0..self.parents.len())];
+            let offset: [f64; D] = normal(rng);
+            std::array::from_fn(|i| parent[i] + self.sigma * offset[i])
+        })
+    }
+}
+
+pub struct Matern<const D: usize> {
+    parents: Vec<[f64; D]>,
+    radius: f64,
+}
+
+impl<const D: usize> Distribution<Point<D>> for Matern<D> {
+    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Point<D> {
+        conditioned(|| {
+            let parent = self.parents[rng.gen_range(0..self.parents.len())];
+            in_ball(rng, parent, self.radius)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    use super::PointProcess;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            "uniform".parse::<PointProcess>().unwrap(),
+            PointProcess::Uniform
+        );
+        assert_eq!(
+            "gaussian:0.1".parse::<PointProcess>().unwrap(),
+            PointProcess::Gaussian { sigma: 0.1 }
+        );
+        assert_eq!(
+            "thomas:20,0.05".parse::<PointProcess>().unwrap(),
+            PointProcess::Thomas {
+                parents: 20,
+                sigma: 0.05
+            }
+        );
+        assert!("beta:2".parse::<PointProcess>().is_err());
+        assert!("gaussian:-1".parse::<PointProcess>().is_err());
+        assert!("cauchy".parse::<PointProcess>().is_err());
+        for parents in ["2.7", "0", "-3", "1e12"] {
+            let process = format!("thomas:{parents},0.05");
+            assert!(process.parse::<PointProcess>().is_err(), "{process}");
+        }
+
+        for name in ["ball", "beta:0.5,2", "matern:10,0.05"] {
+            assert_eq!(name.parse::<PointProcess>().unwrap().to_string(), name);
+        }
+    }
+
+    #[test]
+    fn sphere() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let sampler = PointProcess::Sphere.sampler::<3>(&mut rng);
+
+        for _ in 0..1000 {
+            let point = rng.sample(&sampler).to_float();
+            let radius = point.iter().map(|x| (x - 0.5).powi(2)).sum::<f64>().sqrt();
+            assert!((radius - 0.5).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn matern() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let process = PointProcess::Matern {
+            parents: 1,
+            radius: 0.01,
+        };
+        let sampler = process.sampler::<2>(&mut rng);
+        let first = rng.sample(&sampler).to_float();
+
+        for _ in 0..1000 {
+            let point = rng.sample(&sampler).to_float();
+            let dist = ((point[0] - first[0]).powi(2) + (point[1] - first[1]).powi(2)).sqrt();
+            assert!(dist <= 0.02 + 1e-9);
+        }
+    }
+}
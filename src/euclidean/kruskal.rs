@@ -1,22 +1,37 @@
 use std::{cell::Cell, ops::Index};
 
-use crate::euclidean::point::fixed_to_float;
+use clap::ValueEnum;
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{bench::Bench, euclidean::point::fixed_to_float};
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd)]
-pub struct Point(u32);
+pub struct Point(pub u32);
 
 pub struct UnionFind {
+    // First index represented by the set
+    offset: u32,
     links: Vec<Cell<Point>>,
 }
 
 impl UnionFind {
     pub fn new(size: u32) -> Self {
+        Self::with_offset(0, size)
+    }
+
+    /// A set over the indices `offset..offset + size`, so that subranges of
+    /// the global point array can be handled without reindexing their edges
+    pub fn with_offset(offset: u32, size: u32) -> Self {
         Self {
-            links: (0..size).map(|x| Cell::new(Point(x))).collect(),
+            offset,
+            links: (offset..offset + size)
+                .map(|x| Cell::new(Point(x)))
+                .collect(),
         }
     }
 
-    pub fn unite(&self, mut u: Point, mut v: Point) -> bool {
+    pub fn unite(&self, u: Point, v: Point) -> bool {
         let mut x = self.root(u);
         let mut y = self.root(v);
         if x == y {
@@ -52,18 +67,15 @@ impl UnionFind {
         }
     }
 
+    /// Makes every point a singleton again
     pub fn clear(&mut self) {
-        // TODO: see if this is actually faster than
-        // just reallocating everything
-        let size = self.links.len() as u32;
-        self.links
-            .iter_mut()
-            .zip((0..size))
-            .map(|(x, i)| *x = Cell::new(Point(i)));
+        for (link, i) in self.links.iter_mut().zip(self.offset..) {
+            *link.get_mut() = Point(i);
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Dist2Edge {
     pub u: u32,
     pub v: u32,
@@ -77,44 +89,268 @@ pub enum MaybeEdge {
     Maybe(Dist2Edge),
 }
 
+/// How `kruskal` orders the edges
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Strategy {
+    /// Comparison sort of every edge
+    Comparison,
+    /// LSD radix sort on the squared lengths
+    #[default]
+    Radix,
+    /// Filter-Kruskal, partitioning around pivots and dropping the heavy
+    /// edges which already lie within a component before sorting them
+    Filter,
+}
+
+// Below this many edges a comparison sort wins over the radix passes
+const RADIX_MIN: usize = 256;
+
+// Filter-Kruskal stops partitioning below this many edges
+const FILTER_MIN: usize = 1024;
+
+/// Runs Kruskal's algorithm over `edges` on top of the forest already
+/// stored in `union`, returning (in increasing order) the edges joining
+/// two components which also pass `filter`. Every strategy behaves like a
+/// stable sort, callers rely on ties keeping their order.
 pub fn kruskal(
-    edges: &mut Vec<Dist2Edge>,
-    size: usize,
+    edges: &mut [Dist2Edge],
+    union: &UnionFind,
+    strategy: Strategy,
     filter: impl Fn(&Dist2Edge) -> bool,
 ) -> Vec<Dist2Edge> {
-    if size == 0 {
-        return vec![];
+    let mut mst = Vec::new();
+    match strategy {
+        Strategy::Comparison => {
+            edges.sort_by_key(|x| x.dist2);
+            scan(edges, union, &filter, &mut mst);
+        }
+        Strategy::Radix => {
+            radix_sort(edges);
+            scan(edges, union, &filter, &mut mst);
+        }
+        Strategy::Filter => filter_kruskal(edges.to_vec(), union, &filter, &mut mst),
+    }
+
+    mst
+}
+
+/// Kruskal over edges already in increasing order
+fn scan(
+    edges: &[Dist2Edge],
+    union: &UnionFind,
+    filter: &impl Fn(&Dist2Edge) -> bool,
+    mst: &mut Vec<Dist2Edge>,
+) {
+    for &edge in edges {
+        if union.unite(Point(edge.u), Point(edge.v)) && filter(&edge) {
+            mst.push(edge);
+        }
+    }
+}
+
+/// Stable LSD radix sort on `dist2`, one byte per pass, skipping the high
+/// bytes which are zero for every edge
+pub fn radix_sort(edges: &mut [Dist2Edge]) {
+    if edges.len() < RADIX_MIN {
+        edges.sort_by_key(|x| x.dist2);
+        return;
     }
 
-    // Sort by weight
-    edges.sort_by(|x, y| x.dist2.cmp(&y.dist2));
+    let max = edges.iter().map(|x| x.dist2).max().unwrap_or(0);
+    let passes = (u64::BITS - max.leading_zeros()).div_ceil(u8::BITS);
 
-    let mut mst = Vec::with_capacity(size - 1);
-    let mut counter = size - 1;
-    let mut union = UnionFind::new(size as u32);
+    let mut buffer = edges.to_vec();
+    let (mut from, mut to) = (&mut *edges, buffer.as_mut_slice());
+    for pass in 0..passes {
+        let shift = pass * u8::BITS;
+        let digit = |edge: &Dist2Edge| (edge.dist2 >> shift) as u8 as usize;
 
-    for &mut edge in edges {
-        if counter == 0 {
-            break;
+        let mut starts = [0_usize; 256];
+        for edge in from.iter() {
+            starts[digit(edge)] += 1;
         }
 
-        if union.unite(Point(edge.u), Point(edge.v)) {
-            if filter(&edge) {
-                mst.push(edge);
-            }
-            counter -= 1;
+        let mut sum = 0;
+        for start in &mut starts {
+            (*start, sum) = (sum, sum + *start);
         }
+
+        for &edge in from.iter() {
+            let start = &mut starts[digit(&edge)];
+            to[*start] = edge;
+            *start += 1;
+        }
+
+        std::mem::swap(&mut from, &mut to);
+    }
+
+    // An odd number of passes leaves the result in the buffer
+    if passes % 2 == 1 {
+        edges.copy_from_slice(&buffer);
     }
+}
 
-    assert_eq!(counter, 0);
+fn filter_kruskal(
+    mut edges: Vec<Dist2Edge>,
+    union: &UnionFind,
+    filter: &impl Fn(&Dist2Edge) -> bool,
+    mst: &mut Vec<Dist2Edge>,
+) {
+    if edges.len() < FILTER_MIN {
+        radix_sort(&mut edges);
+        scan(&edges, union, filter, mst);
+        return;
+    }
 
-    mst
+    // Median of three, the partitions keep the order of the edges so that
+    // ties are still handled like a stable sort
+    let mut samples = [0, edges.len() / 2, edges.len() - 1].map(|i| edges[i].dist2);
+    samples.sort_unstable();
+    let pivot = samples[1];
+
+    let (mut light, mut heavy): (Vec<_>, Vec<_>) =
+        edges.iter().copied().partition(|x| x.dist2 < pivot);
+    if light.is_empty() {
+        // The pivot is the lightest length, split off every edge of it
+        (light, heavy) = edges.iter().copied().partition(|x| x.dist2 <= pivot);
+        if heavy.is_empty() {
+            scan(&edges, union, filter, mst);
+            return;
+        }
+    }
+    drop(edges);
+
+    filter_kruskal(light, union, filter, mst);
+    heavy.retain(|x| union.root(Point(x.u)) != union.root(Point(x.v)));
+    filter_kruskal(heavy, union, filter, mst);
 }
 
 impl Index<Point> for UnionFind {
     type Output = Cell<Point>;
 
     fn index(&self, index: Point) -> &Self::Output {
-        &self.links[index.0 as usize]
+        &self.links[(index.0 - self.offset) as usize]
+    }
+}
+
+/// The kernels of `randmst bench`: every strategy on 2^16 random edges
+/// between 2^12 points
+pub fn kernels(bench: &Bench) {
+    let mut rng = SmallRng::seed_from_u64(0);
+    let edges: Vec<Dist2Edge> = (0..1 << 16)
+        .map(|_| Dist2Edge {
+            u: rng.gen_range(0..1 << 12),
+            v: rng.gen_range(0..1 << 12),
+            dist2: rng.gen_range(0..1 << 40),
+        })
+        .collect();
+
+    for strategy in Strategy::value_variants() {
+        let name = format!("kruskal {strategy:?}").to_lowercase();
+        bench.run(&name, || {
+            let union = UnionFind::new(1 << 12);
+            kruskal(&mut edges.clone(), &union, *strategy, |_| true)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    use super::{kruskal, radix_sort, Dist2Edge, Point, Strategy, UnionFind};
+
+    /// Random edges with many ties, to catch unstable orderings
+    fn edges(count: usize, bits: u32, rng: &mut SmallRng) -> Vec<Dist2Edge> {
+        (0..count)
+            .map(|i| Dist2Edge {
+                u: rng.gen_range(0..100),
+                v: rng.gen_range(0..100),
+                dist2: rng.gen_range(0..1 << bits),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn clear() {
+        let mut union = UnionFind::with_offset(10, 5);
+        union.unite(Point(10), Point(12));
+        union.unite(Point(12), Point(14));
+        assert!(union.root(Point(10)) == union.root(Point(14)));
+
+        union.clear();
+        assert!((10..15).all(|i| union.root(Point(i)) == Point(i)));
+    }
+
+    #[test]
+    fn radix_is_stable() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        for (count, bits) in [(10, 4), (1000, 4), (5000, 20), (5000, 63)] {
+            let mut expected = edges(count, bits, &mut rng);
+            let mut sorted = expected.clone();
+            expected.sort_by_key(|x| x.dist2);
+            radix_sort(&mut sorted);
+            assert_eq!(sorted, expected);
+        }
+    }
+
+    #[test]
+    fn strategies_agree() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        for (count, bits) in [(50, 3), (3000, 6), (20000, 30)] {
+            let edges = edges(count, bits, &mut rng);
+            let run = |strategy| {
+                let union = UnionFind::new(100);
+                kruskal(&mut edges.clone(), &union, strategy, |x| x.u % 7 != 0)
+            };
+
+            let expected = run(Strategy::Comparison);
+            assert_eq!(run(Strategy::Radix), expected);
+            assert_eq!(run(Strategy::Filter), expected);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "benchmark"))]
+mod benchmarks {
+    extern crate test;
+    use rand::{thread_rng, Rng};
+    use test::{black_box, Bencher};
+
+    use super::{kruskal, Dist2Edge, Strategy, UnionFind};
+
+    const POINTS: u32 = 1 << 12;
+    const EDGES: usize = 1 << 16;
+
+    fn bench(b: &mut Bencher, strategy: Strategy) {
+        let mut rng = thread_rng();
+        let edges: Vec<Dist2Edge> = (0..EDGES)
+            .map(|_| Dist2Edge {
+                u: rng.gen_range(0..POINTS),
+                v: rng.gen_range(0..POINTS),
+                dist2: rng.gen_range(0..1 << 40),
+            })
+            .collect();
+
+        b.iter(|| {
+            let union = UnionFind::new(POINTS);
+            black_box(kruskal(&mut edges.clone(), &union, strategy, |_| true))
+        });
+    }
+
+    #[bench]
+    fn comparison(b: &mut Bencher) {
+        bench(b, Strategy::Comparison);
+    }
+
+    #[bench]
+    fn radix(b: &mut Bencher) {
+        bench(b, Strategy::Radix);
+    }
+
+    #[bench]
+    fn filter(b: &mut Bencher) {
+        bench(b, Strategy::Filter);
     }
 }
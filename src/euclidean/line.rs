@@ -0,0 +1,134 @@
+use super::point::{Point, MANTISSA_BITS};
+
+/// An edge of the tree on the line, with its exact fixed point length
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gap {
+    pub u: u32,
+    pub v: u32,
+    pub length: u64,
+}
+
+/// Computes the minimum spanning tree of points on the line, which joins
+/// neighbors in sorted order. On the torus the points live on a circle of
+/// unit length, and the tree is the cycle of gaps without its longest one.
+/// Only the first coordinate of the points is used.
+pub fn line<const D: usize>(points: &[Point<D>], torus: bool, order: &mut Vec<u32>) -> Vec<Gap> {
+    order.clear();
+    order.extend(0..points.len() as u32);
+    order.sort_unstable_by_key(|&i| points[i as usize].coords()[0]);
+
+    let x = |i: u32| points[i as usize].coords()[0];
+    let mut gaps: Vec<Gap> = order
+        .windows(2)
+        .map(|pair| Gap {
+            u: pair[0],
+            v: pair[1],
+            length: x(pair[1]) - x(pair[0]),
+        })
+        .collect();
+
+    if let (true, Some(&first), Some(&last)) = (torus, order.first(), order.last()) {
+        let wrap = Gap {
+            u: last,
+            v: first,
+            length: (1 << MANTISSA_BITS) - (x(last) - x(first)),
+        };
+
+        // The first longest gap leaves, so ties drop the same edge every time
+        let longest = gaps
+            .iter()
+            .enumerate()
+            .max_by_key(|(i, gap)| (gap.length, std::cmp::Reverse(*i)))
+            .map(|(i, gap)| (i, gap.length));
+        match longest {
+            Some((i, length)) if length >= wrap.length => gaps[i] = wrap,
+            _ => {}
+        }
+    }
+
+    gaps
+}
+
+#[cfg(test)]
+mod tests {
+    use average::{Estimate, MeanWithError};
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    use super::line;
+    use crate::euclidean::{
+        mst_powers,
+        point::{Point, MANTISSA_BITS},
+        Options, Workspace,
+    };
+
+    const TRIALS: u64 = 20000;
+
+    fn points(coords: &[u64]) -> Vec<Point<1>> {
+        coords.iter().map(|&x| Point::from([x])).collect()
+    }
+
+    #[test]
+    fn gaps() {
+        let unit = 1 << MANTISSA_BITS;
+        let points = points(&[unit / 2, 0, unit / 8, unit - unit / 8]);
+
+        let edges = line(&points, false, &mut Vec::new());
+        let lengths: Vec<u64> = edges.iter().map(|gap| gap.length).collect();
+        assert_eq!(lengths, [unit / 8, unit * 3 / 8, unit * 3 / 8]);
+        assert_eq!((edges[0].u, edges[0].v), (1, 2));
+
+        // Around the circle the gap between the ends is the shortest
+        let edges = line(&points, true, &mut Vec::new());
+        let total: u64 = edges.iter().map(|gap| gap.length).sum();
+        assert_eq!(edges.len(), 3);
+        assert_eq!(total, unit - unit * 3 / 8);
+        assert!(edges.iter().any(|gap| (gap.u, gap.v) == (3, 1)));
+
+        assert!(line(&points[..1], true, &mut Vec::new()).is_empty());
+
+        // A point rounding up to the right edge stays there
+        let edge = Point::<1>::from_float([1.0 - f64::EPSILON / 4.0]);
+        assert_eq!(edge.coords(), [unit - 1]);
+    }
+
+    /// Compares the mean total length over many trials with `expected`
+    fn assert_mean(size: u32, torus: bool, expected: f64) {
+        let options = Options {
+            torus,
+            ..Options::default()
+        };
+        let mut workspace = Workspace::<1>::default();
+
+        let mut total = MeanWithError::new();
+        for seed in 0..TRIALS {
+            let rng = SmallRng::seed_from_u64(seed);
+            let sums = mst_powers::<1>(size, rng, &options, &[1.0], &mut workspace).unwrap();
+            total.add(sums.sums()[0]);
+        }
+
+        let deviation = (total.mean() - expected).abs() / total.error();
+        assert!(
+            deviation < 5.0,
+            "{size} points: mean {} against {expected} ({deviation:.1} standard errors)",
+            total.mean()
+        );
+    }
+
+    #[test]
+    fn uniform_expectation() {
+        // The tree spans from the minimum to the maximum of the points
+        for size in [2, 10, 100] {
+            let n = size as f64;
+            assert_mean(size, false, (n - 1.0) / (n + 1.0));
+        }
+    }
+
+    #[test]
+    fn torus_expectation() {
+        // The longest of the `n` uniform spacings of the circle has mean `H_n / n`
+        for size in [2, 10, 100] {
+            let harmonic: f64 = (1..=size).map(|k| 1.0 / k as f64).sum();
+            assert_mean(size, true, 1.0 - harmonic / size as f64);
+        }
+    }
+}
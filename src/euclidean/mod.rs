@@ -1,29 +1,270 @@
-use rand::{Rng, RngCore};
+use clap::ValueEnum;
+use rand::{rngs::SmallRng, Rng, RngCore, SeedableRng};
 use rand_distr::Distribution;
+use serde::{Deserialize, Serialize};
 
 use self::{
+    boruvka::boruvka, delaunay::delaunay, kruskal::Dist2Edge, line::line, point::fixed_to_float,
+    spatial::SpatialVec,
+};
+pub use self::{
+    distribution::PointProcess,
+    kruskal::Strategy,
     morton::Morton,
     point::{Hypercube, Point},
-    spatial::{recurse_test, SpatialVec},
+};
+use crate::{
+    bench::Bench,
+    error::{check_size, Error, Result},
+    functional::PowerSums,
+    tree::Edge,
 };
 
 mod aabb;
+mod boruvka;
+mod delaunay;
+mod distribution;
 mod kruskal;
+mod line;
 mod merge;
 mod morton;
 mod point;
+pub mod reference;
 mod spatial;
 
-pub fn mst<const D: usize>(size: u32, mut rng: impl RngCore) -> f64
+/// Slices of the spatial decomposition with at most this many cells are
+/// merged serially by default
+pub const DEFAULT_GRAIN: usize = 1 << 12;
+
+/// Algorithms computing the minimum spanning tree of a point set
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Algorithm {
+    /// Kruskal on a Morton ordered grid, merging cells bottom up
+    #[default]
+    Grid,
+    /// Dual-tree Borůvka on a kd-tree
+    Boruvka,
+    /// Kruskal on the Delaunay triangulation (2D only)
+    Delaunay,
+}
+
+/// Settings of the Euclidean model
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Distribution of the points
+    pub process: PointProcess,
+
+    /// How the tree of the points is computed
+    pub algorithm: Algorithm,
+
+    /// How Kruskal orders the edges in the grid and Delaunay algorithms
+    pub strategy: Strategy,
+
+    /// Slices with more cells than this merge their halves in parallel,
+    /// so that a single huge trial can use the whole thread pool
+    pub grain: usize,
+
+    /// Wraps the unit interval into a circle, only on the line
+    pub torus: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            process: PointProcess::Uniform,
+            algorithm: Algorithm::default(),
+            strategy: Strategy::default(),
+            grain: DEFAULT_GRAIN,
+            torus: false,
+        }
+    }
+}
+
+/// Memory reused by the trials run on one thread, so that they stop
+/// allocating once they have seen their largest size
+pub struct Workspace<const D: usize> {
+    points: Vec<Point<D>>,
+    spatial: SpatialVec<D>,
+    // Sorted order of the points on the line
+    order: Vec<u32>,
+}
+
+impl<const D: usize> Default for Workspace<D> {
+    fn default() -> Self {
+        Self {
+            points: Vec::new(),
+            spatial: SpatialVec::default(),
+            order: Vec::new(),
+        }
+    }
+}
+
+/// Checks that a tree on `size` points can be computed with `options`
+fn validate<const D: usize>(size: usize, options: &Options) -> Result<()> {
+    check_size(size, u32::MAX as usize)?;
+    match options.algorithm {
+        Algorithm::Delaunay if D != 2 => Err(Error::PlanarOnly {
+            algorithm: "delaunay",
+            dimension: D,
+        }),
+        _ if options.torus && D != 1 => Err(Error::LineOnly {
+            option: "torus",
+            dimension: D,
+        }),
+        _ => Ok(()),
+    }
+}
+
+pub fn mst<const D: usize>(size: u32, rng: impl RngCore) -> Result<f64>
+where
+    Hypercube<D>: Distribution<Point<D>>,
+    Point<D>: Morton,
+{
+    let mut workspace = Workspace::default();
+    let sums = mst_powers::<D>(size, rng, &Options::default(), &[1.0], &mut workspace)?;
+    Ok(sums.sums()[0])
+}
+
+/// Samples a tree on points drawn from `options.process` and accumulates
+/// `Σ|e|^α` for each of the `alphas`, plus the longest edge. Works with the
+/// squared lengths, so `α = 2` never takes a square root.
+pub fn mst_powers<const D: usize>(
+    size: u32,
+    mut rng: impl RngCore,
+    options: &Options,
+    alphas: &[f64],
+    workspace: &mut Workspace<D>,
+) -> Result<PowerSums>
+where
+    Hypercube<D>: Distribution<Point<D>>,
+    Point<D>: Morton,
+{
+    validate::<D>(size as usize, options)?;
+    let distr = options.process.sampler::<D>(&mut rng);
+    let Workspace {
+        points,
+        spatial,
+        order,
+    } = workspace;
+    points.clear();
+    points.extend((0..size).map(|_| rng.sample(&distr)));
+
+    let mut sums = PowerSums::new(alphas);
+    if D == 1 {
+        // The gaps are exact, which their squares would not be
+        for gap in line(points, options.torus, order) {
+            sums.add(fixed_to_float(gap.length));
+        }
+    } else {
+        for edge in tree(points, options, spatial) {
+            sums.add_squared(fixed_to_float(edge.dist2));
+        }
+    }
+
+    Ok(sums)
+}
+
+/// Samples the points of a single trial from `process`
+pub fn sample_points<const D: usize>(
+    size: u32,
+    mut rng: impl RngCore,
+    process: &PointProcess,
+) -> Vec<[f64; D]>
 where
     Hypercube<D>: Distribution<Point<D>>,
+{
+    let distr = process.sampler::<D>(&mut rng);
+    (0..size).map(|_| rng.sample(&distr).to_float()).collect()
+}
+
+/// Computes the minimum spanning tree of the given points of `[0, 1)^D`.
+/// The tree is found on fixed point coordinates, but the lengths of its
+/// edges are those between the given points.
+pub fn emst<const D: usize>(points: &[[f64; D]], options: &Options) -> Result<Vec<Edge>>
+where
     Point<D>: Morton,
 {
-    let mut spatial = SpatialVec::new((0..size).map(|_| rng.sample(Hypercube::<D>)));
+    validate::<D>(points.len(), options)?;
+
+    let fixed: Vec<Point<D>> = points.iter().map(|&p| Point::from_float(p)).collect();
+    let pairs: Vec<(u32, u32)> = match D {
+        1 => line(&fixed, options.torus, &mut Vec::new())
+            .into_iter()
+            .map(|gap| (gap.u, gap.v))
+            .collect(),
+        _ => tree(&fixed, options, &mut SpatialVec::default())
+            .into_iter()
+            .map(|edge| (edge.u, edge.v))
+            .collect(),
+    };
+
+    let period = options.torus.then_some(1.0);
+    Ok(pairs
+        .into_iter()
+        .map(|(u, v)| Edge {
+            u,
+            v,
+            length: distance(&points[u as usize], &points[v as usize], period),
+        })
+        .collect())
+}
+
+/// Distance between two points, around a torus of side `period` if given
+pub fn distance(a: &[f64], b: &[f64], period: Option<f64>) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| {
+            let d = (a - b).abs();
+            let d = period.map_or(d, |period| d.min(period - d));
+            d * d
+        })
+        .sum::<f64>()
+        .sqrt()
+}
+
+/// Edges of the tree, indexed by the position of the points in `points`.
+/// The grid algorithm reuses the memory of `spatial`.
+fn tree<const D: usize>(
+    points: &[Point<D>],
+    options: &Options,
+    spatial: &mut SpatialVec<D>,
+) -> Vec<Dist2Edge>
+where
+    Point<D>: Morton,
+{
+    match options.algorithm {
+        Algorithm::Grid => {
+            spatial.rebuild(points.iter().copied());
+            spatial.mst(options.grain, options.strategy)
+        }
+        Algorithm::Boruvka => boruvka(points),
+        Algorithm::Delaunay => delaunay(points, options.strategy),
+    }
+}
+
+/// The kernels of `randmst bench`, on 2^18 points in dimension 4
+pub fn kernels(bench: &Bench) {
+    const SIZE: usize = 1 << 18;
+
+    let mut rng = SmallRng::seed_from_u64(0);
+    bench.run("generate 262144 points", || {
+        (&mut rng)
+            .sample_iter(Hypercube::<4>)
+            .take(SIZE)
+            .collect::<Vec<Point<4>>>()
+    });
 
-    recurse_test(spatial.as_slice());
+    let points: Vec<Point<4>> = rng.sample_iter(Hypercube::<4>).take(SIZE).collect();
+    bench.run("morton code 262144 points", || {
+        points
+            .iter()
+            .map(|x| x.morton_encode(4))
+            .collect::<Vec<usize>>()
+    });
 
-    0.0
+    point::kernels(bench);
+    kruskal::kernels(bench);
 }
 
 #[cfg(all(test, feature = "benchmark"))]
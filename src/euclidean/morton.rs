@@ -8,10 +8,10 @@ fn space_bits_2(x: u32) -> u32 {
     let x = (x | x << 8) & b_8;
     let x = (x | x << 4) & b_4;
     let x = (x | x << 2) & b_2;
-    let x = (x | x << 1) & b_1;
-    x
+    (x | x << 1) & b_1
 }
 
+#[allow(clippy::unusual_byte_groupings)]
 fn space_bits_3(x: u32) -> u32 {
     let (b_2, b_4, b_8) = (
         0b00000000_001001_001001_001001_001001,
@@ -20,8 +20,7 @@ fn space_bits_3(x: u32) -> u32 {
     );
     let x = (x | x << 8) & b_8;
     let x = (x | x << 4) & b_4;
-    let x = (x | x << 2) & b_2;
-    x
+    (x | x << 2) & b_2
 }
 
 fn space_bits_4(x: u32) -> u32 {
@@ -32,8 +31,7 @@ fn space_bits_4(x: u32) -> u32 {
     );
     let x = (x | x << 12) & b_12;
     let x = (x | x << 6) & b_6;
-    let x = (x | x << 3) & b_3;
-    x
+    (x | x << 3) & b_3
 }
 
 pub fn morton_encode_2(point: [u32; 2]) -> usize {
@@ -51,13 +49,38 @@ pub fn morton_encode_4(point: [u32; 4]) -> usize {
         | space_bits_4(point[3]) << 3) as usize
 }
 
+/// Interleaves the lowest `resolution` bits of each coordinate of a grid cell,
+/// bit-for-bit compatible with the specialized encoders above
+pub fn morton_encode_cell<const D: usize>(cell: [u32; D], resolution: u32) -> usize {
+    let mut code = 0;
+    for bit in 0..resolution as usize {
+        for (axis, &x) in cell.iter().enumerate() {
+            code |= ((x as usize >> bit) & 1) << (bit * D + axis);
+        }
+    }
+    code
+}
+
+/// Inverse of [`morton_encode_cell`]
+pub fn morton_decode_cell<const D: usize>(code: usize, resolution: u32) -> [u32; D] {
+    let mut cell = [0; D];
+    for bit in 0..resolution as usize {
+        for (axis, x) in cell.iter_mut().enumerate() {
+            *x |= (((code >> (bit * D + axis)) & 1) as u32) << bit;
+        }
+    }
+    cell
+}
+
 pub trait Morton {
     fn morton_encode(&self, resolution: u32) -> usize;
 }
 
 #[cfg(test)]
 mod tests {
-    use super::morton_encode_2;
+    use super::{
+        morton_decode_cell, morton_encode_2, morton_encode_3, morton_encode_4, morton_encode_cell,
+    };
 
     #[test]
     fn simple_2() {
@@ -68,8 +91,33 @@ mod tests {
     }
 
     #[test]
-    fn simple_3() {}
+    fn simple_3() {
+        assert_eq!(morton_encode_3([0b1, 0b1, 0b1]), 0b111);
+        assert_eq!(morton_encode_3([0b0, 0b0, 0b1]), 0b100);
+        assert_eq!(morton_encode_3([0b11, 0b00, 0b00]), 0b001001);
+    }
+
+    #[test]
+    fn simple_4() {
+        assert_eq!(morton_encode_4([0b1, 0b0, 0b0, 0b1]), 0b1001);
+        assert_eq!(morton_encode_4([0b10, 0b0, 0b0, 0b0]), 0b10000);
+    }
 
     #[test]
-    fn simple_4() {}
+    fn generic_matches_specialized() {
+        for x in 0..16 {
+            for y in 0..16 {
+                assert_eq!(morton_encode_cell([x, y], 4), morton_encode_2([x, y]));
+                assert_eq!(morton_decode_cell::<2>(morton_encode_2([x, y]), 4), [x, y]);
+                assert_eq!(
+                    morton_encode_cell([x, y, x ^ y], 4),
+                    morton_encode_3([x, y, x ^ y])
+                );
+                assert_eq!(
+                    morton_encode_cell([y, x, y, x], 4),
+                    morton_encode_4([y, x, y, x])
+                );
+            }
+        }
+    }
 }
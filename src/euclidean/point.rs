@@ -1,9 +1,12 @@
 use std::{
     fmt::Display,
+    hint::black_box,
     ops::{Add, Mul, Sub},
 };
 
 use super::morton::{morton_encode_2, morton_encode_3, morton_encode_4, Morton};
+use crate::bench::Bench;
+use rand::{rngs::SmallRng, Rng, SeedableRng};
 use rand_distr::Distribution;
 
 #[derive(Clone, Copy, Debug)]
@@ -11,7 +14,7 @@ pub struct Point<const D: usize>([u64; D]);
 
 impl<const D: usize> Display for Point<D> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("{:?}", self.0.map(|x| fixed_to_float(x))))?;
+        f.write_fmt(format_args!("{:?}", self.0.map(fixed_to_float)))?;
 
         Ok(())
     }
@@ -32,6 +35,15 @@ impl<const D: usize> Point<D> {
         self.0
     }
 
+    /// Converts a point of the unit cube `[0, 1)^D` to fixed point
+    pub fn from_float(coords: [f64; D]) -> Self {
+        Self(coords.map(float_to_fixed))
+    }
+
+    pub fn to_float(self) -> [f64; D] {
+        self.0.map(fixed_to_float)
+    }
+
     pub fn distance2_fixed(&self, point: &Point<D>) -> u64 {
         let mut sum = 0_u128;
 
@@ -47,32 +59,26 @@ impl<const D: usize> Point<D> {
         let mut sum = 0_u128;
 
         for i in 0..D {
-            let delt = (self.0[i] as u128);
+            let delt = self.0[i] as u128;
             sum = sum.wrapping_add(delt.wrapping_mul(delt));
         }
 
         sum.wrapping_shr(MANTISSA_BITS) as u64
     }
 
-    fn round_to(&self, bits: u32) -> [u32; D] {
+    /// Coordinates of the grid cell containing this point, at a grid
+    /// resolution of `bits` bits per axis
+    pub fn round_to(&self, bits: u32) -> [u32; D] {
         self.0
             .map(|x| (x.wrapping_shr(MANTISSA_BITS - bits)) as u32)
     }
 
     pub fn min(&self, rhs: Self) -> Self {
-        let mut result = [0; D];
-        for i in 0..D {
-            result[i] = self.0[i].min(rhs.0[i]);
-        }
-        Self(result)
+        Self(std::array::from_fn(|i| self.0[i].min(rhs.0[i])))
     }
 
     pub fn max(&self, rhs: Self) -> Self {
-        let mut result = [0; D];
-        for i in 0..D {
-            result[i] = self.0[i].max(rhs.0[i]);
-        }
-        Self(result)
+        Self(std::array::from_fn(|i| self.0[i].max(rhs.0[i])))
     }
 }
 
@@ -80,11 +86,7 @@ impl<const D: usize> Add for Point<D> {
     type Output = Point<D>;
 
     fn add(self, rhs: Self) -> Self::Output {
-        let mut result = [0; D];
-        for i in 0..D {
-            result[i] = self.0[i].wrapping_add(rhs.0[i]);
-        }
-        Self(result)
+        Self(std::array::from_fn(|i| self.0[i].wrapping_add(rhs.0[i])))
     }
 }
 
@@ -92,15 +94,11 @@ impl<const D: usize> Sub for Point<D> {
     type Output = Point<D>;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        let mut result = [0; D];
-        for i in 0..D {
-            result[i] = self.0[i].wrapping_sub(rhs.0[i]);
-        }
-        Self(result)
+        Self(std::array::from_fn(|i| self.0[i].wrapping_sub(rhs.0[i])))
     }
 }
 
-const MANTISSA_BITS: u32 = 51;
+pub const MANTISSA_BITS: u32 = 51;
 const FIXED_UNIT: u64 = 0x0008_0000_0000_0000;
 const FIXED_MASK: u64 = 0x0007_FFFF_FFFF_FFFF;
 
@@ -108,6 +106,12 @@ pub fn fixed_to_float(x: u64) -> f64 {
     x as f64 / (FIXED_UNIT as f64)
 }
 
+/// Assumes `x` lies in `[0, 1)`, anything at or past the right edge
+/// staying on the last fixed point value
+pub fn float_to_fixed(x: f64) -> u64 {
+    ((x * FIXED_UNIT as f64) as u64).min(FIXED_MASK)
+}
+
 pub struct Hypercube<const D: usize>;
 
 macro_rules! hypercube_impl {
@@ -120,10 +124,17 @@ macro_rules! hypercube_impl {
     };
 }
 
+hypercube_impl!(1);
 hypercube_impl!(2);
 hypercube_impl!(3);
 hypercube_impl!(4);
 
+impl Morton for Point<1> {
+    fn morton_encode(&self, resolution: u32) -> usize {
+        self.round_to(resolution)[0] as usize
+    }
+}
+
 impl Morton for Point<2> {
     fn morton_encode(&self, resolution: u32) -> usize {
         morton_encode_2(self.round_to(resolution))
@@ -142,6 +153,28 @@ impl Morton for Point<4> {
     }
 }
 
+/// The kernels of `randmst bench`, in dimension 4
+pub fn kernels(bench: &Bench) {
+    let mut rng = SmallRng::seed_from_u64(0);
+    let points: Vec<Point<4>> = (&mut rng).sample_iter(Hypercube::<4>).take(2).collect();
+    bench.run("distance2 fixed", || {
+        black_box(points[0]).distance2_fixed(&black_box(points[1]))
+    });
+
+    let (point0, point1): ([f64; 4], [f64; 4]) = rng.gen();
+    bench.run("distance2 float", || {
+        let (point0, point1) = (black_box(point0), black_box(point1));
+        let mut dist2 = 0.0;
+        for i in 0..4 {
+            dist2 += (point0[i] - point1[i]) * (point0[i] - point1[i]);
+        }
+        dist2
+    });
+
+    let fixed: u64 = rng.gen();
+    bench.run("fixed to float", || fixed_to_float(black_box(fixed)));
+}
+
 #[cfg(all(test, feature = "benchmark"))]
 mod tests {
     use rand::{thread_rng, Rng};
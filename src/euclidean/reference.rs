@@ -0,0 +1,91 @@
+use super::{
+    boruvka::boruvka,
+    delaunay::delaunay,
+    kruskal::{self, Dist2Edge, Strategy, UnionFind},
+    line::line,
+    morton::Morton,
+    point::Point,
+    spatial::SpatialVec,
+};
+
+/// Prim's algorithm on the complete graph of the points, in `O(n^2)` time.
+/// Far too slow for real use but simple enough to be obviously right, so
+/// the fast algorithms are checked against it.
+pub fn prim<const D: usize>(points: &[Point<D>]) -> Vec<Dist2Edge> {
+    let mut tree = Vec::with_capacity(points.len().saturating_sub(1));
+
+    // Squared distance to the tree and closest tree vertex, for every
+    // vertex still outside of it
+    let mut nearest = vec![(u64::MAX, 0_u32); points.len()];
+    let mut outside: Vec<u32> = (1..points.len() as u32).collect();
+    let mut last = 0;
+
+    while !outside.is_empty() {
+        let mut best = 0;
+        for (k, &v) in outside.iter().enumerate() {
+            let dist2 = points[last as usize].distance2_fixed(&points[v as usize]);
+            if dist2 < nearest[v as usize].0 {
+                nearest[v as usize] = (dist2, last);
+            }
+
+            if nearest[v as usize] < nearest[outside[best] as usize] {
+                best = k;
+            }
+        }
+
+        let v = outside.swap_remove(best);
+        let (dist2, u) = nearest[v as usize];
+        tree.push(Dist2Edge { u, v, dist2 });
+        last = v;
+    }
+
+    tree
+}
+
+/// Total squared length of the edges, exactly
+pub fn weight(edges: &[Dist2Edge]) -> u128 {
+    edges.iter().map(|edge| edge.dist2 as u128).sum()
+}
+
+pub fn is_spanning_tree(size: usize, edges: &[Dist2Edge]) -> bool {
+    let union = UnionFind::new(size as u32);
+    edges.len() + 1 == size
+        && edges
+            .iter()
+            .all(|edge| union.unite(kruskal::Point(edge.u), kruskal::Point(edge.v)))
+}
+
+/// Runs every algorithm and Kruskal strategy on the points, and names the
+/// first one whose tree is not a spanning tree exactly as light as Prim's
+pub fn disagreement<const D: usize>(points: &[Point<D>]) -> Option<String>
+where
+    Point<D>: Morton,
+{
+    let grid = |grain, strategy| SpatialVec::new(points.iter().copied()).mst(grain, strategy);
+
+    let mut results = Vec::new();
+    if D == 1 {
+        let gaps = line(points, false, &mut Vec::new()).into_iter();
+        let edges = gaps.map(|gap| Dist2Edge {
+            u: gap.u,
+            v: gap.v,
+            dist2: points[gap.u as usize].distance2_fixed(&points[gap.v as usize]),
+        });
+        results.push(("line".to_string(), edges.collect()));
+    } else {
+        results.push(("boruvka".to_string(), boruvka(points)));
+        results.push(("parallel grid".to_string(), grid(1, Strategy::Radix)));
+        for strategy in [Strategy::Comparison, Strategy::Radix, Strategy::Filter] {
+            results.push((format!("{strategy:?} grid"), grid(usize::MAX, strategy)));
+            if D == 2 {
+                results.push((format!("{strategy:?} delaunay"), delaunay(points, strategy)));
+            }
+        }
+    }
+
+    let expected = weight(&prim(points));
+    results
+        .into_iter()
+        .find(|(_, edges)| !is_spanning_tree(points.len(), edges) || weight(edges) != expected)
+        .map(|(name, _)| name)
+}
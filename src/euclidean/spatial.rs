@@ -1,11 +1,13 @@
-use rand::{thread_rng, Rng};
+use std::ops::Range;
+
 use smallvec::SmallVec;
 
 use super::{
     aabb::AABB,
-    kruskal::{kruskal, Dist2Edge, MaybeEdge},
-    morton::Morton,
-    point::{self, Point},
+    kruskal::{self, kruskal, Dist2Edge, MaybeEdge, Strategy, UnionFind},
+    morton::{morton_decode_cell, morton_encode_cell, Morton},
+    point::{Point, MANTISSA_BITS},
+    reference::prim,
 };
 
 /// A set of points representing the recursion level
@@ -31,117 +33,325 @@ impl<const D: usize> BaseCell<D> {
         self.points.push(index);
         self.bounds = AABB::expand(self.bounds.clone(), point);
     }
+
+    fn clear(&mut self) {
+        self.points.clear();
+        self.bounds = AABB::default();
+    }
+}
+
+/// Read-only data shared between all slices of a `SpatialVec`
+pub struct Grid<const D: usize> {
+    // Points sorted by Z-order
+    zord: Vec<(Point<D>, u32)>,
+
+    // Index into `zord` of the first point of each cell, with a sentinel
+    starts: Vec<u32>,
+
+    // Number of bits per axis used to address the cells
+    resolution: u32,
+
+    // How the edges of each cell and merge are ordered
+    strategy: Strategy,
+}
+
+impl<const D: usize> Grid<D> {
+    fn point(&self, index: u32) -> Point<D> {
+        self.zord[index as usize].0
+    }
+
+    fn edge(&self, u: u32, v: u32) -> Dist2Edge {
+        Dist2Edge {
+            u,
+            v,
+            dist2: self.point(u).distance2_fixed(&self.point(v)),
+        }
+    }
+
+    /// Indices into `zord` of the points in the cells `offset..offset + len`
+    fn points(&self, offset: usize, len: usize) -> Range<u32> {
+        self.starts[offset]..self.starts[offset + len]
+    }
+
+    /// Squared side length of a cell. Any two points closer than this
+    /// lie in neighbouring cells.
+    fn threshold(&self) -> u64 {
+        1 << (MANTISSA_BITS - 2 * self.resolution)
+    }
+
+    /// Pushes all of the edges shorter than `threshold` between two cells
+    fn connect(&self, first: usize, second: usize, edges: &mut Vec<Dist2Edge>) {
+        let threshold = self.threshold();
+        for u in self.points(first, 1) {
+            for v in self.points(second, 1) {
+                let edge = self.edge(u, v);
+                if edge.dist2 < threshold {
+                    edges.push(edge);
+                }
+            }
+        }
+    }
+
+    /// Pushes the short edges between neighbouring cells which lie on opposite
+    /// sides of the split of the aligned block of cells `offset..offset + len`.
+    /// Every pair of neighbouring cells is split exactly once in the recursion.
+    fn cross_edges(&self, offset: usize, len: usize, edges: &mut Vec<Dist2Edge>) {
+        let bits = len.ilog2() as usize;
+        let axis = (bits - 1) % D;
+
+        // The block is a box, split in half along `axis`
+        let min = morton_decode_cell::<D>(offset, self.resolution);
+        let max: [u32; D] =
+            std::array::from_fn(|i| min[i] + (1 << (bits / D + usize::from(i < bits % D))));
+        let face = (min[axis] + max[axis]) / 2 - 1;
+
+        let (mut lo, mut hi) = (min, max);
+        lo[axis] = face;
+        hi[axis] = face + 1;
+
+        for_each_cell(lo, hi, |cell| {
+            let lo: [u32; D] = std::array::from_fn(|i| match i == axis {
+                true => face + 1,
+                false => cell[i].saturating_sub(1).max(min[i]),
+            });
+            let hi: [u32; D] = std::array::from_fn(|i| match i == axis {
+                true => face + 2,
+                false => (cell[i] + 2).min(max[i]),
+            });
+
+            let first = morton_encode_cell(cell, self.resolution);
+            for_each_cell(lo, hi, |neighbor| {
+                self.connect(first, morton_encode_cell(neighbor, self.resolution), edges)
+            });
+        });
+    }
+
+    /// Calls `f` on every point in the cells at Chebyshev distance exactly
+    /// `radius` from `cell`
+    fn shell(&self, cell: [u32; D], radius: u32, mut f: impl FnMut(u32)) {
+        let side = 1 << self.resolution;
+        let lo = cell.map(|x| x.saturating_sub(radius));
+        let hi = cell.map(|x| (x + radius + 1).min(side));
+
+        for_each_cell(lo, hi, |other| {
+            let distance = (0..D).map(|i| cell[i].abs_diff(other[i])).max();
+            if distance == Some(radius) {
+                self.points(morton_encode_cell(other, self.resolution), 1)
+                    .for_each(&mut f);
+            }
+        });
+    }
+}
+
+/// Calls `f` on every cell in the box `lo..hi`
+fn for_each_cell<const D: usize>(lo: [u32; D], hi: [u32; D], mut f: impl FnMut([u32; D])) {
+    if (0..D).any(|i| lo[i] >= hi[i]) {
+        return;
+    }
+
+    let mut cell = lo;
+    loop {
+        f(cell);
+
+        let mut i = 0;
+        loop {
+            if i == D {
+                return;
+            }
+
+            cell[i] += 1;
+            if cell[i] < hi[i] {
+                break;
+            }
+
+            cell[i] = lo[i];
+            i += 1;
+        }
+    }
 }
 
 pub enum SpatialSliceSplit<'a, const D: usize> {
     Split(SpatialSlice<'a, D>, SpatialSlice<'a, D>),
-    Root(&'a mut BaseCell<D>, &'a [(Point<D>, u32)]),
+    Root(&'a mut BaseCell<D>),
 }
 
 /// Represents a `half slice` of the spatial vector
 pub struct SpatialSlice<'a, const D: usize> {
     cells: &'a mut [BaseCell<D>],
-    zord: &'a [(Point<D>, u32)],
+
+    // Morton code of the first cell in the slice
+    offset: usize,
+    grid: &'a Grid<D>,
 }
 
 impl<const D: usize> SpatialSlice<'_, D> {
-    pub fn split<'a>(&'a mut self) -> SpatialSliceSplit<'a, D> {
+    pub fn split(&mut self) -> SpatialSliceSplit<'_, D> {
         use SpatialSliceSplit::*;
 
         if self.cells.len() > 1 {
             // Shouldn't need to round up because slice length will always
             // be a power of 2, but just in case
-            let (s1, s2) = self.cells.split_at_mut((self.cells.len() + 1) / 2);
+            let mid = self.cells.len().div_ceil(2);
+            let (s1, s2) = self.cells.split_at_mut(mid);
             Split(
                 SpatialSlice {
                     cells: s1,
-                    zord: &self.zord[..],
+                    offset: self.offset,
+                    grid: self.grid,
                 },
                 SpatialSlice {
                     cells: s2,
-                    zord: &self.zord[..],
+                    offset: self.offset + mid,
+                    grid: self.grid,
                 },
             )
         } else {
-            Root(&mut self.cells[0], &self.zord[..])
+            Root(&mut self.cells[0])
         }
     }
 }
 
 /// A slice that has been recursively merged all the way down
 pub struct MergedSlice<const D: usize> {
-    // slice: SpatialSlice<'a, D>,
     partial_graph: Vec<MaybeEdge>,
     bounds: AABB<D>,
 }
 
 impl<const D: usize> MergedSlice<D> {
-    /// Recursively merge a slice
-    pub fn recursive_merge(mut slice: SpatialSlice<'_, D>) -> Self {
+    /// Recursively merge a slice, the halves of slices with more than `grain`
+    /// cells are merged in parallel
+    pub fn recursive_merge(mut slice: SpatialSlice<'_, D>, grain: usize) -> Self {
         use SpatialSliceSplit::*;
 
+        let (grid, offset, len) = (slice.grid, slice.offset, slice.cells.len());
         match slice.split() {
-            Split(s1, s2) => Self::merge(Self::recursive_merge(s1), Self::recursive_merge(s2)),
-            Root(root, zord) => Self::kruskal_root(root, zord),
+            Split(s1, s2) if len > grain => {
+                let (first, second) = rayon::join(
+                    || Self::recursive_merge(s1, grain),
+                    || Self::recursive_merge(s2, grain),
+                );
+                Self::merge(grid, offset, len, first, second)
+            }
+            Split(s1, s2) => Self::merge(
+                grid,
+                offset,
+                len,
+                Self::recursive_merge(s1, grain),
+                Self::recursive_merge(s2, grain),
+            ),
+            Root(root) => Self::kruskal_root(grid, offset, root),
         }
     }
 
-    pub fn merge(mut first: MergedSlice<D>, mut second: MergedSlice<D>) -> Self {
+    pub fn merge(
+        grid: &Grid<D>,
+        offset: usize,
+        len: usize,
+        mut first: MergedSlice<D>,
+        mut second: MergedSlice<D>,
+    ) -> Self {
         first.partial_graph.append(&mut second.partial_graph);
 
-        Self {
-            bounds: AABB::union(first.bounds, second.bounds),
-            partial_graph: first.partial_graph,
-        }
+        let mut edges = Vec::new();
+        grid.cross_edges(offset, len, &mut edges);
+
+        Self::classify(
+            grid,
+            grid.points(offset, len),
+            first.partial_graph,
+            edges,
+            AABB::union(first.bounds, second.bounds),
+        )
     }
 
-    pub fn kruskal_root(root: &mut BaseCell<D>, zord: &[(Point<D>, u32)]) -> Self {
+    pub fn kruskal_root(grid: &Grid<D>, offset: usize, root: &mut BaseCell<D>) -> Self {
+        let threshold = grid.threshold();
         let mut edges: Vec<Dist2Edge> = Vec::new();
         for i in 0..root.points.len() {
             for j in 0..i {
-                let u = root.points[i];
-                let v = root.points[j];
-                edges.push(Dist2Edge {
-                    u: i as u32,
-                    v: j as u32,
-                    dist2: zord[u as usize].0.distance2_fixed(&zord[v as usize].0),
-                })
+                let edge = grid.edge(root.points[i], root.points[j]);
+                if edge.dist2 < threshold {
+                    edges.push(edge);
+                }
             }
         }
 
-        // First pass
-        let first = kruskal(&mut edges, root.points.len(), |_| true);
+        Self::classify(
+            grid,
+            grid.points(offset, 1),
+            Vec::new(),
+            edges,
+            root.bounds.clone(),
+        )
+    }
 
-        // Add boundary points
-        let boundary = root.points.len() as u32;
-        for (i, &point) in root.points.iter().enumerate() {
-            edges.push(Dist2Edge {
-                u: i as u32,
-                v: boundary, // represents the boundary `node`
-                dist2: root.bounds.dist2(zord[point as usize].0),
-            })
+    /// Runs the two Kruskal passes over the `Maybe` edges of `partial_graph`
+    /// together with the new candidate `edges`, all of which are shorter than
+    /// a cell, so that every such edge within `points` is accounted for
+    fn classify(
+        grid: &Grid<D>,
+        points: Range<u32>,
+        partial_graph: Vec<MaybeEdge>,
+        mut edges: Vec<Dist2Edge>,
+        bounds: AABB<D>,
+    ) -> Self {
+        let mut sure = Vec::new();
+        for edge in partial_graph {
+            match edge {
+                MaybeEdge::Sure(edge) => sure.push(edge),
+                MaybeEdge::Maybe(edge) => edges.push(edge),
+            }
         }
 
         // slightly larger union find set to account for the boundary `node`
-        let second = kruskal(&mut edges, root.points.len() + 1, |edge| {
-            (edge.u != boundary) & (edge.v != boundary)
-        });
+        let mut union = UnionFind::with_offset(points.start, points.len() as u32 + 1);
+        let forest = |union: &UnionFind| {
+            for edge in &sure {
+                union.unite(kruskal::Point(edge.u), kruskal::Point(edge.v));
+            }
+        };
+
+        // First pass, anything dropped here closes a cycle of shorter edges
+        forest(&union);
+        let first = kruskal(&mut edges, &union, grid.strategy, |_| true);
+
+        // Add boundary points, a path leaving the box is at least as long
+        // as the distance to its boundary
+        let boundary = points.end; // represents the boundary `node`
+        let cutoff = first.last().map_or(0, |edge| edge.dist2);
+        let mut edges = first.clone();
+        for i in points {
+            let dist2 = bounds.dist2(grid.point(i));
+            if dist2 <= cutoff {
+                edges.push(Dist2Edge {
+                    u: i,
+                    v: boundary,
+                    dist2,
+                });
+            }
+        }
+
+        // Edges surviving the second pass can't be beaten by anything outside
+        union.clear();
+        forest(&union);
+        let second = kruskal(&mut edges, &union, grid.strategy, |edge| edge.v != boundary);
 
-        // Merge the results of the Kruskal's
-        let partial_graph: Vec<MaybeEdge> = first
+        // The sort is stable, so `second` is a subsequence of `first`
+        let mut second = second.into_iter().peekable();
+        let partial_graph = sure
             .into_iter()
-            .map(|x| {
-                if let Ok(_) = second.binary_search_by(|y| y.dist2.cmp(&x.dist2)) {
-                    MaybeEdge::Sure(x)
+            .map(MaybeEdge::Sure)
+            .chain(first.into_iter().map(|edge| {
+                if second.next_if_eq(&edge).is_some() {
+                    MaybeEdge::Sure(edge)
                 } else {
-                    MaybeEdge::Maybe(x)
+                    MaybeEdge::Maybe(edge)
                 }
-            })
+            }))
             .collect();
 
         Self {
-            bounds: root.bounds.clone(),
-            // TODO: remember to map the points back into the correct representation!!!
+            bounds,
             partial_graph,
         }
     }
@@ -150,8 +360,28 @@ impl<const D: usize> MergedSlice<D> {
 pub struct SpatialVec<const D: usize> {
     cells: Vec<BaseCell<D>>,
 
-    // Points sorted by Z-order
-    zord: Vec<(Point<D>, u32)>,
+    // Index in the input of each point of `zord`
+    original: Vec<u32>,
+    grid: Grid<D>,
+
+    // Scratch space for the Z-ordering, kept to be reused
+    keyed: Vec<(Point<D>, u32, u32)>,
+}
+
+impl<const D: usize> Default for SpatialVec<D> {
+    fn default() -> Self {
+        Self {
+            cells: Vec::new(),
+            original: Vec::new(),
+            grid: Grid {
+                zord: Vec::new(),
+                starts: Vec::new(),
+                resolution: 0,
+                strategy: Strategy::default(),
+            },
+            keyed: Vec::new(),
+        }
+    }
 }
 
 impl<const D: usize> SpatialVec<D>
@@ -159,83 +389,233 @@ where
     Point<D>: Morton,
 {
     // Play with this to get best average
-    const LEN_FACTOR: usize = match D {
-        _ => 1,
-    };
-
-    pub fn new(points: impl Iterator<Item = Point<D>> + ExactSizeIterator) -> Self {
-        let mut cells = (points.len() * Self::LEN_FACTOR) as u32;
-        let mut resolution = cells.ilog2() / D as u32;
-        cells = 1 << (D as u32 * resolution);
+    const LEN_FACTOR: usize = 1;
 
-        if points.len() < u32::BITS as usize * D {
-            unimplemented!();
-        }
+    // Below this many points Prim on the complete graph is faster
+    const DIRECT: usize = u32::BITS as usize * D;
 
-        println!("cells: {cells}");
-        println!("resolution: {resolution}");
+    pub fn new(points: impl ExactSizeIterator<Item = Point<D>>) -> Self {
+        let mut spatial = Self::default();
+        spatial.rebuild(points);
+        spatial
+    }
 
-        let mut cells: Vec<BaseCell<D>> = (0..cells).map(|_| BaseCell::<D>::new()).collect();
-        let mut zord: Vec<(Point<D>, u32)> = points
-            .map(|p| (p, p.morton_encode(resolution) as u32))
-            .collect();
+    /// Replaces the points, reusing the memory of the previous ones
+    pub fn rebuild(&mut self, points: impl ExactSizeIterator<Item = Point<D>>) {
+        // Small inputs live in a single cell, their tree is computed directly
+        let resolution = match points.len() < Self::DIRECT {
+            true => 0,
+            false => ((points.len() * Self::LEN_FACTOR) as u32).ilog2() / D as u32,
+        };
+        let cells = 1_u32 << (D as u32 * resolution);
+
+        self.cells.truncate(cells as usize);
+        self.cells.iter_mut().for_each(BaseCell::clear);
+        self.cells.resize_with(cells as usize, BaseCell::new);
+
+        let keyed = &mut self.keyed;
+        keyed.clear();
+        keyed.extend(
+            points
+                .enumerate()
+                .map(|(i, p)| (p, p.morton_encode(resolution) as u32, i as u32)),
+        );
 
         // Z-ordering of points in global array
-        zord.sort_by(|x, y| x.1.cmp(&y.1));
+        keyed.sort_by_key(|x| x.1);
+        self.original.clear();
+        self.original.extend(keyed.iter().map(|x| x.2));
+
+        let grid = &mut self.grid;
+        grid.resolution = resolution;
+        grid.zord.clear();
+        grid.zord.extend(keyed.iter().map(|&(p, z, _)| (p, z)));
 
         // Cache friendly insertion (according to the Z-ordering)
-        for (i, &(point, z)) in zord.iter().enumerate() {
-            cells[z as usize].push(i as u32, point);
+        let starts = &mut grid.starts;
+        starts.clear();
+        starts.resize(self.cells.len() + 1, 0);
+        for (i, &(point, z)) in grid.zord.iter().enumerate() {
+            self.cells[z as usize].push(i as u32, point);
+            starts[z as usize + 1] = i as u32 + 1;
         }
 
-        let mut avg = 0_usize;
-        let mut heap_count = 0_usize;
-        for cell in &cells {
-            if cell.points.len() != 0 {
-                avg += cell.points.len();
-            }
-
-            if cell.points.len() > 8 {
-                heap_count += 1;
-            }
+        // Empty cells start where the previous one ends
+        for i in 1..starts.len() {
+            starts[i] = starts[i].max(starts[i - 1]);
         }
-        println!("avg: {}", avg / cells.len());
-        println!("heap: {}", heap_count);
-
-        Self { cells, zord }
     }
 
-    pub fn as_slice(&mut self) -> SpatialSlice<D> {
+    pub fn as_slice(&mut self) -> SpatialSlice<'_, D> {
         SpatialSlice {
             cells: self.cells.as_mut_slice(),
-            zord: &self.zord[..],
+            offset: 0,
+            grid: &self.grid,
         }
     }
-}
 
-pub fn recurse_test<const D: usize>(mut slice: SpatialSlice<D>) -> bool
-where
-    Point<D>: Morton,
-{
-    let merge = MergedSlice::recursive_merge(slice);
-    println!("world bound: {}", merge.bounds);
-    println!("partial_graph: {}", merge.partial_graph.len());
-
-    true
-    // match slice.split() {
-    //     SpatialSliceSplit::Split(a, b) => {
-    //         return recurse_test(a) || recurse_test(b);
-    //     }
-    //     SpatialSliceSplit::Root(root) => {
-    //         let dist = point::fixed_to_float(root.bounds.diag2());
-    //         if root.points.len() != 0 {
-    //             println!("{}, {dist} size {}", root.bounds, root.points.len());
-    //         }
-    //
-    //         return root.points.len() != 0;
-    //     }
-    // }
+    /// Computes the minimum spanning tree of the points, with edges indexing
+    /// into the points in the order they were given
+    pub fn mst(&mut self, grain: usize, strategy: Strategy) -> Vec<Dist2Edge> {
+        self.grid.strategy = strategy;
+        let mut tree = match self.grid.zord.len() < Self::DIRECT {
+            true => {
+                let points: Vec<Point<D>> = self.grid.zord.iter().map(|x| x.0).collect();
+                prim(&points)
+            }
+            false => {
+                let merged = MergedSlice::recursive_merge(self.as_slice(), grain);
+                self.complete(merged)
+            }
+        };
+
+        for edge in &mut tree {
+            edge.u = self.original[edge.u as usize];
+            edge.v = self.original[edge.v as usize];
+        }
+
+        tree
+    }
+
+    /// Finishes the tree from the fully merged vector. At the top level every
+    /// edge shorter than a cell is known, the remaining long edges must touch a
+    /// point outside of the largest component so they are found by searching
+    /// growing shells of cells around those points.
+    fn complete(&self, merged: MergedSlice<D>) -> Vec<Dist2Edge> {
+        let grid = &self.grid;
+        let size = grid.zord.len() as u32;
+        let union = UnionFind::new(size);
+
+        let mut tree: Vec<Dist2Edge> = merged
+            .partial_graph
+            .into_iter()
+            .map(|edge| match edge {
+                MaybeEdge::Sure(edge) | MaybeEdge::Maybe(edge) => edge,
+            })
+            .collect();
+
+        for edge in &tree {
+            union.unite(kruskal::Point(edge.u), kruskal::Point(edge.v));
+        }
+
+        if tree.len() + 1 >= size as usize {
+            return tree;
+        }
+
+        let root = |u: u32| union.root(kruskal::Point(u)).0;
+        let mut sizes = vec![0_u32; size as usize];
+        for u in 0..size {
+            sizes[root(u) as usize] += 1;
+        }
+
+        let largest = (0..size).max_by_key(|&u| sizes[u as usize]).unwrap();
+        let stragglers: Vec<u32> = (0..size).filter(|&u| root(u) != largest).collect();
+
+        let side = 1 << grid.resolution;
+        let mut pending: Vec<Dist2Edge> = Vec::new();
+        let mut scanned = 0;
+        let mut radius = 1;
+
+        while tree.len() + 1 < size as usize {
+            radius += 1;
+
+            // Edges shorter than `radius` cells lie within `radius` shells
+            for shell in scanned..=radius {
+                for &u in &stragglers {
+                    let cell = grid.point(u).round_to(grid.resolution);
+                    grid.shell(cell, shell, |v| {
+                        if root(u) != root(v) {
+                            pending.push(grid.edge(u, v));
+                        }
+                    });
+                }
+            }
+            scanned = radius + 1;
+
+            let threshold = match radius < side {
+                true => grid.threshold() * radius as u64 * radius as u64,
+                false => u64::MAX,
+            };
+
+            pending.sort_by_key(|edge| edge.dist2);
+            let split = pending.partition_point(|edge| edge.dist2 < threshold);
+            for edge in pending.drain(..split) {
+                if union.unite(kruskal::Point(edge.u), kruskal::Point(edge.v)) {
+                    tree.push(edge);
+                }
+            }
+        }
+
+        tree
+    }
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+    use rand_distr::Distribution;
+
+    use super::SpatialVec;
+    use crate::{
+        euclidean::{
+            kruskal::{Dist2Edge, Strategy},
+            morton::Morton,
+            point::{Hypercube, Point},
+        },
+        validate,
+    };
+
+    const INSTANCES: u64 = 1000;
+
+    fn grid<const D: usize>(points: &[Point<D>], grain: usize, strategy: Strategy) -> Vec<Dist2Edge>
+    where
+        Point<D>: Morton,
+    {
+        SpatialVec::new(points.iter().copied()).mst(grain, strategy)
+    }
+
+    /// Runs every algorithm against Prim on seeded instances of varying size
+    /// and point process, the same as `randmst validate`
+    fn differential<const D: usize>()
+    where
+        Hypercube<D>: Distribution<Point<D>>,
+        Point<D>: Morton,
+    {
+        let check = validate::euclidean::<D>(INSTANCES, 256, 0);
+        assert_eq!(check.failure, None);
+    }
+
+    #[test]
+    fn rebuild() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut spatial = SpatialVec::default();
+        for size in [5000, 100, 1, 20, 1000] {
+            let points: Vec<Point<3>> = (&mut rng).sample_iter(Hypercube::<3>).take(size).collect();
+            spatial.rebuild(points.iter().copied());
+            assert_eq!(
+                spatial.mst(usize::MAX, Strategy::Radix),
+                grid(&points, usize::MAX, Strategy::Radix)
+            );
+        }
+    }
+
+    #[test]
+    fn differential_1() {
+        differential::<1>();
+    }
+
+    #[test]
+    fn differential_2() {
+        differential::<2>();
+    }
+
+    #[test]
+    fn differential_3() {
+        differential::<3>();
+    }
+
+    #[test]
+    fn differential_4() {
+        differential::<4>();
+    }
+}
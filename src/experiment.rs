@@ -0,0 +1,353 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    euclidean::{self, Algorithm, Options, PointProcess, Strategy},
+    runner::{self, Schedule},
+    statistics::Accumulator,
+    store::{Key, Span, Store},
+};
+
+/// An experiment file, holding runs which are executed in order
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Experiment {
+    /// Directory receiving the results and the resolved configuration,
+    /// named after the file by default
+    pub output: Option<PathBuf>,
+
+    #[serde(rename = "run")]
+    pub runs: Vec<Run>,
+}
+
+/// A model and the sizes to run it at, with the same meaning as the flags
+/// of `sample`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Run {
+    /// Name of the run in the results, `run-<i>` by default
+    pub name: Option<String>,
+    /// Dimension of the points, `0` for the random complete graph
+    pub dimension: u32,
+    pub points: Vec<u32>,
+    pub trials: u32,
+    #[serde(default = "default_alphas")]
+    pub alphas: Vec<f64>,
+    #[serde(default)]
+    pub max_edge: bool,
+    #[serde(default)]
+    pub distribution: PointProcess,
+    #[serde(default)]
+    pub algorithm: Algorithm,
+    #[serde(default)]
+    pub kruskal: Strategy,
+    #[serde(default = "default_grain")]
+    pub grain: usize,
+    #[serde(default)]
+    pub torus: bool,
+    #[serde(default)]
+    pub poisson: bool,
+    #[serde(default)]
+    pub schedule: Schedule,
+    /// Drawn at random when missing, and recorded in the resolved file.
+    /// The trials of the `k`th size start from `seed + k·2^32`.
+    pub seed: Option<u64>,
+}
+
+fn default_alphas() -> Vec<f64> {
+    vec![1.0]
+}
+
+fn default_grain() -> usize {
+    euclidean::DEFAULT_GRAIN
+}
+
+/// The formats an experiment can be written in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Toml,
+    Json,
+}
+
+impl Format {
+    /// JSON for `.json` files, TOML otherwise
+    pub fn detect(path: &Path) -> Self {
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("json") => Self::Json,
+            _ => Self::Toml,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Toml => "toml",
+            Self::Json => "json",
+        }
+    }
+}
+
+impl Experiment {
+    pub fn parse(text: &str, format: Format) -> Result<Self> {
+        Ok(match format {
+            Format::Toml => toml::from_str(text)?,
+            Format::Json => serde_json::from_str(text)?,
+        })
+    }
+
+    pub fn to_string(&self, format: Format) -> Result<String> {
+        Ok(match format {
+            Format::Toml => toml::to_string_pretty(self)?,
+            Format::Json => serde_json::to_string_pretty(self)?,
+        })
+    }
+
+    /// Reads an experiment and resolves it, so that it can be replayed
+    /// exactly from the copy written to its output directory
+    pub fn read(path: &Path) -> Result<Self> {
+        let text =
+            fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        let mut experiment = Self::parse(&text, Format::detect(path))
+            .with_context(|| format!("parsing {}", path.display()))?;
+
+        if experiment.output.is_none() {
+            experiment.output = Some(path.with_extension(""));
+        }
+        experiment.resolve()?;
+
+        Ok(experiment)
+    }
+
+    /// Names the runs and draws their missing seeds
+    pub fn resolve(&mut self) -> Result<()> {
+        for (i, run) in self.runs.iter_mut().enumerate() {
+            run.name.get_or_insert_with(|| format!("run-{i}"));
+            // Small enough to be an integer in both TOML and JSON
+            run.seed
+                .get_or_insert_with(|| thread_rng().gen_range(0..1 << 53));
+            run.check()?;
+        }
+
+        let mut names: Vec<_> = self.runs.iter().map(|run| run.name()).collect();
+        names.sort_unstable();
+        match names.windows(2).find(|pair| pair[0] == pair[1]) {
+            Some(pair) => Err(anyhow!("several runs are named `{}`", pair[0])),
+            None => Ok(()),
+        }
+    }
+
+    /// Creates the output directory with the resolved configuration in it.
+    /// Results already in there are an error, unless `resume` asks for the
+    /// new trials to be added to them.
+    pub fn create_output(&self, format: Format, resume: bool) -> Result<Output> {
+        let dir = self
+            .output
+            .clone()
+            .ok_or_else(|| anyhow!("the experiment has no output directory"))?;
+        fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+
+        let output = Output::new(&dir);
+        let store = match (output.store.exists(), resume) {
+            (true, true) => Store::read(&output.store)?,
+            (true, false) => {
+                return Err(anyhow!(
+                    "{} already holds results, pass --resume to add to them",
+                    dir.display()
+                ))
+            }
+            (false, _) => Store::default(),
+        };
+
+        let config = dir.join(format!("config.{}", format.extension()));
+        fs::write(&config, self.to_string(format)?)
+            .with_context(|| format!("writing {}", config.display()))?;
+
+        Ok(Output {
+            store: output.store,
+            csv: output.csv,
+            results: store,
+        })
+    }
+}
+
+impl Run {
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or_default()
+    }
+
+    /// Seed of the trials of the `k`th size
+    pub fn seed(&self, k: usize) -> Option<u64> {
+        self.seed.map(|seed| seed.wrapping_add((k as u64) << 32))
+    }
+
+    pub fn key(&self, points: u32, functional: String) -> Key {
+        Key {
+            dimension: self.dimension,
+            points,
+            functional,
+            distribution: self.distribution.clone(),
+            torus: self.torus,
+            poisson: self.poisson,
+        }
+    }
+
+    pub fn options(&self) -> Options {
+        Options {
+            process: self.distribution.clone(),
+            algorithm: self.algorithm,
+            strategy: self.kruskal,
+            grain: self.schedule.grain(self.grain),
+            torus: self.torus,
+        }
+    }
+
+    fn check(&self) -> Result<()> {
+        if self.points.is_empty() {
+            return Err(anyhow!("run `{}` has no sizes", self.name()));
+        }
+
+        check_functionals(&self.alphas, self.dimension, &self.distribution)
+            .with_context(|| format!("in run `{}`", self.name()))
+    }
+}
+
+/// Rejects the exponents and point processes which make no sense for the
+/// model in `dimension`
+pub fn check_functionals(alphas: &[f64], dimension: u32, process: &PointProcess) -> Result<()> {
+    if let Some(alpha) = alphas.iter().find(|&&alpha| alpha <= 0.0 || alpha.is_nan()) {
+        return Err(anyhow!("exponent {alpha} must be positive!"));
+    }
+
+    if dimension == 0 && *process != PointProcess::Uniform {
+        return Err(anyhow!("distributions only apply to the Euclidean model!"));
+    }
+
+    Ok(())
+}
+
+/// The results of an experiment, rewritten after every size so that an
+/// interrupted run can be resumed
+pub struct Output {
+    store: PathBuf,
+    csv: PathBuf,
+    results: Store,
+}
+
+impl Output {
+    fn new(dir: &Path) -> Self {
+        Self {
+            store: dir.join("results.json"),
+            csv: dir.join("results.csv"),
+            results: Store::default(),
+        }
+    }
+
+    /// The number of trials already recorded for a size of `run`, which
+    /// all of its functionals share
+    pub fn done(&self, run: &Run, points: u32) -> u64 {
+        runner::labels(&run.alphas, run.max_edge)
+            .into_iter()
+            .map(|label| match self.results.get(&run.key(points, label)) {
+                Some(accumulator) => accumulator.count,
+                None => 0,
+            })
+            .min()
+            .unwrap_or_default()
+    }
+
+    /// The statistics recorded for a size of `run`
+    pub fn functionals(&self, run: &Run, points: u32) -> Vec<(String, Accumulator)> {
+        runner::labels(&run.alphas, run.max_edge)
+            .into_iter()
+            .map(|label| {
+                let accumulator = self.results.get(&run.key(points, label.clone()));
+                (label, accumulator.copied().unwrap_or_default())
+            })
+            .collect()
+    }
+
+    /// Adds the statistics of the new `trials` to the totals and saves them
+    pub fn record(
+        &mut self,
+        run: &Run,
+        points: u32,
+        trials: Span,
+        functionals: &[(String, Accumulator)],
+    ) -> Result<()> {
+        for (label, accumulator) in functionals {
+            let key = run.key(points, label.clone());
+            self.results.add(run.name(), key, &[trials], accumulator)?;
+        }
+
+        self.results.write(&self.store)?;
+        self.results.write_csv(&self.csv)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Experiment, Format};
+    use crate::euclidean::{Algorithm, PointProcess};
+
+    const TOML: &str = r#"
+        output = "results/plane"
+
+        [[run]]
+        name = "plane"
+        dimension = 2
+        points = [100, 1000]
+        trials = 10
+        distribution = "gaussian:0.1"
+        algorithm = "delaunay"
+
+        [[run]]
+        dimension = 0
+        points = [50]
+        trials = 5
+        alphas = [1, 2]
+        seed = 3
+    "#;
+
+    #[test]
+    fn parse_and_resolve() {
+        let mut experiment = Experiment::parse(TOML, Format::Toml).unwrap();
+        experiment.resolve().unwrap();
+
+        let [plane, complete] = &experiment.runs[..] else {
+            panic!("expected two runs");
+        };
+        assert_eq!(plane.distribution, PointProcess::Gaussian { sigma: 0.1 });
+        assert_eq!(plane.algorithm, Algorithm::Delaunay);
+        assert_eq!(plane.alphas, [1.0]);
+        assert!(plane.seed.is_some());
+        assert_eq!(complete.name(), "run-1");
+        assert_eq!(complete.seed, Some(3));
+
+        // The resolved experiment reads back the same in both formats
+        for format in [Format::Toml, Format::Json] {
+            let text = experiment.to_string(format).unwrap();
+            let again = Experiment::parse(&text, format).unwrap();
+            assert_eq!(again.to_string(format).unwrap(), text);
+            assert_eq!(again.runs[0].seed, plane.seed);
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let unknown = TOML.replace("trials = 5", "trials = 5\ntrails = 5");
+        assert!(Experiment::parse(&unknown, Format::Toml).is_err());
+
+        let process = TOML.replace("seed = 3", "seed = 3\ndistribution = \"ball\"");
+        let mut experiment = Experiment::parse(&process, Format::Toml).unwrap();
+        assert!(experiment.resolve().is_err());
+
+        let twice = TOML.replace("dimension = 0", "name = \"plane\"\ndimension = 0");
+        let mut experiment = Experiment::parse(&twice, Format::Toml).unwrap();
+        assert!(experiment.resolve().is_err());
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::tree::Edge;
+
+/// Files to serialize a tree into
+#[derive(clap::Args, Debug, Default)]
+pub struct ExportArgs {
+    #[arg(
+        long,
+        help = "Write the tree as a Graphviz DOT graph, edge lengths as `len`"
+    )]
+    pub dot: Option<PathBuf>,
+
+    #[arg(long, help = "Write the tree as a GraphML graph")]
+    pub graphml: Option<PathBuf>,
+
+    #[arg(long, help = "Draw the tree in the unit square as an SVG (2D only)")]
+    pub svg: Option<PathBuf>,
+}
+
+impl ExportArgs {
+    /// Writes every requested file. `points` are the positions of the
+    /// vertices in `[0, 1)^2`, when the tree is planar.
+    pub fn export(
+        &self,
+        edges: &[Edge],
+        vertices: usize,
+        points: Option<&[[f64; 2]]>,
+    ) -> Result<()> {
+        if let Some(path) = &self.dot {
+            write_dot(create(path)?, edges)?;
+        }
+
+        if let Some(path) = &self.graphml {
+            write_graphml(create(path)?, edges, vertices)?;
+        }
+
+        if let Some(path) = &self.svg {
+            let points =
+                points.ok_or_else(|| anyhow!("SVG output needs two dimensional points"))?;
+            write_svg(create(path)?, points, edges)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn create(path: &Path) -> Result<BufWriter<File>> {
+    let file = File::create(path).with_context(|| format!("creating {}", path.display()))?;
+    Ok(BufWriter::new(file))
+}
+
+/// Writes the tree as an undirected Graphviz graph, with the edge lengths
+/// as the `len` attribute, the preferred length of an edge for `neato`
+pub fn write_dot(mut out: impl Write, edges: &[Edge]) -> Result<()> {
+    writeln!(out, "graph mst {{")?;
+    for edge in edges {
+        writeln!(out, "  {} -- {} [len={}];", edge.u, edge.v, edge.length)?;
+    }
+    writeln!(out, "}}")?;
+
+    Ok(())
+}
+
+/// Writes the tree as an undirected GraphML graph on `vertices` nodes, with
+/// the edge lengths stored under the `length` key
+pub fn write_graphml(mut out: impl Write, edges: &[Edge], vertices: usize) -> Result<()> {
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+    )?;
+    writeln!(
+        out,
+        r#"  <key id="length" for="edge" attr.name="length" attr.type="double"/>"#
+    )?;
+    writeln!(out, r#"  <graph id="mst" edgedefault="undirected">"#)?;
+
+    for vertex in 0..vertices {
+        writeln!(out, r#"    <node id="n{vertex}"/>"#)?;
+    }
+
+    for edge in edges {
+        writeln!(
+            out,
+            r#"    <edge source="n{}" target="n{}"><data key="length">{}</data></edge>"#,
+            edge.u, edge.v, edge.length
+        )?;
+    }
+
+    writeln!(out, "  </graph>")?;
+    writeln!(out, "</graphml>")?;
+
+    Ok(())
+}
+
+// Side of the drawing in pixels
+const SVG_SIZE: f64 = 1000.0;
+
+/// Draws the points and edges of a planar tree in the unit square, with the
+/// `y` axis pointing up
+pub fn write_svg(mut out: impl Write, points: &[[f64; 2]], edges: &[Edge]) -> Result<()> {
+    let position = |index: u32| {
+        let [x, y] = points[index as usize];
+        (x * SVG_SIZE, (1.0 - y) * SVG_SIZE)
+    };
+
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{SVG_SIZE}" height="{SVG_SIZE}" viewBox="0 0 {SVG_SIZE} {SVG_SIZE}">"#
+    )?;
+    writeln!(
+        out,
+        r#"  <rect width="{SVG_SIZE}" height="{SVG_SIZE}" fill="white" stroke="black"/>"#
+    )?;
+
+    writeln!(out, r#"  <g stroke="steelblue" stroke-width="1">"#)?;
+    for edge in edges {
+        let ((x1, y1), (x2, y2)) = (position(edge.u), position(edge.v));
+        writeln!(
+            out,
+            r#"    <line x1="{x1:.2}" y1="{y1:.2}" x2="{x2:.2}" y2="{y2:.2}"/>"#
+        )?;
+    }
+    writeln!(out, "  </g>")?;
+
+    writeln!(out, r#"  <g fill="black">"#)?;
+    for index in 0..points.len() {
+        let (x, y) = position(index as u32);
+        writeln!(out, r#"    <circle cx="{x:.2}" cy="{y:.2}" r="1.5"/>"#)?;
+    }
+    writeln!(out, "  </g>")?;
+    writeln!(out, "</svg>")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_dot, write_graphml, write_svg};
+    use crate::tree::Edge;
+
+    const EDGES: [Edge; 2] = [
+        Edge {
+            u: 0,
+            v: 1,
+            length: 0.5,
+        },
+        Edge {
+            u: 1,
+            v: 2,
+            length: 0.25,
+        },
+    ];
+
+    #[test]
+    fn dot() {
+        let mut out = Vec::new();
+        write_dot(&mut out, &EDGES).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "graph mst {\n  0 -- 1 [len=0.5];\n  1 -- 2 [len=0.25];\n}\n"
+        );
+    }
+
+    #[test]
+    fn graphml() {
+        let mut out = Vec::new();
+        write_graphml(&mut out, &EDGES, 3).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.matches("<node ").count(), 3);
+        assert_eq!(out.matches("<edge ").count(), 2);
+    }
+
+    #[test]
+    fn svg() {
+        let mut out = Vec::new();
+        write_svg(&mut out, &[[0.0, 0.0], [0.5, 0.0], [0.5, 0.25]], &EDGES).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(r#"<line x1="0.00" y1="1000.00" x2="500.00" y2="1000.00"/>"#));
+        assert_eq!(out.matches("<circle ").count(), 3);
+    }
+}
@@ -0,0 +1,87 @@
+/// Accumulates the power-weighted functionals `Σ|e|^α` of a tree for a
+/// fixed list of exponents, together with the longest edge (`α → ∞`),
+/// in a single pass over its edges
+#[derive(Clone, Debug)]
+pub struct PowerSums {
+    alphas: Vec<f64>,
+    sums: Vec<f64>,
+    max: f64,
+}
+
+impl PowerSums {
+    pub fn new(alphas: &[f64]) -> Self {
+        Self {
+            alphas: alphas.to_vec(),
+            sums: vec![0.0; alphas.len()],
+            max: 0.0,
+        }
+    }
+
+    /// Adds an edge of the given length
+    pub fn add(&mut self, length: f64) {
+        for (sum, &alpha) in self.sums.iter_mut().zip(&self.alphas) {
+            *sum += if alpha == 1.0 {
+                length
+            } else {
+                length.powf(alpha)
+            };
+        }
+
+        self.max = self.max.max(length);
+    }
+
+    /// Adds an edge given its squared length, which avoids the square root
+    /// entirely for `α = 2`
+    pub fn add_squared(&mut self, length2: f64) {
+        for (sum, &alpha) in self.sums.iter_mut().zip(&self.alphas) {
+            *sum += if alpha == 1.0 {
+                length2.sqrt()
+            } else if alpha == 2.0 {
+                length2
+            } else {
+                length2.powf(alpha / 2.0)
+            };
+        }
+
+        if length2 > self.max * self.max {
+            self.max = length2.sqrt();
+        }
+    }
+
+    pub fn alphas(&self) -> &[f64] {
+        &self.alphas
+    }
+
+    /// The accumulated `Σ|e|^α`, in the same order as `alphas`
+    pub fn sums(&self) -> &[f64] {
+        &self.sums
+    }
+
+    /// The length of the longest edge seen so far
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PowerSums;
+
+    #[test]
+    fn squared_matches_plain() {
+        let mut plain = PowerSums::new(&[0.5, 1.0, 2.0, 3.0]);
+        let mut squared = PowerSums::new(&[0.5, 1.0, 2.0, 3.0]);
+
+        for length in [0.25, 0.5, 0.125] {
+            plain.add(length);
+            squared.add_squared(length * length);
+        }
+
+        for (x, y) in plain.sums().iter().zip(squared.sums()) {
+            assert!((x - y).abs() < 1e-12);
+        }
+        assert_eq!(plain.sums()[1], 0.875);
+        assert_eq!(plain.max(), 0.5);
+        assert_eq!(squared.max(), 0.5);
+    }
+}
@@ -1,23 +1,167 @@
 #![cfg_attr(feature = "benchmark", feature(test))]
 #![allow(unused, dead_code)]
-use std::time::{Duration, Instant};
+use std::{
+    fs::File,
+    io::{stdout, BufWriter},
+    path::PathBuf,
+    time::Duration,
+};
 
-use anyhow::{anyhow, Result};
-use average::MeanWithError;
-use clap::Parser;
+use analysis::Scaling;
+use anyhow::{anyhow, Context, Result};
+use clap::{Parser, Subcommand};
 use colored::Colorize;
-use rand::{thread_rng, RngCore};
-use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use euclidean::{Algorithm, Options, PointProcess, Strategy};
+use experiment::{Experiment, Output, Run};
+use export::ExportArgs;
+use model::{MstModel, Visitor};
+use pointset::{Format, PointSet};
+use rand::thread_rng;
+use runner::{Schedule, Trials};
+use statistics::Accumulator;
+use store::{Key, Span, Store};
 
+pub mod analysis;
+pub mod bench;
 pub mod complete;
+pub mod error;
 pub mod euclidean;
+pub mod experiment;
+pub mod export;
+pub mod functional;
+pub mod model;
+pub mod pointset;
+pub mod progress;
+pub mod runner;
+pub mod statistics;
+pub mod store;
+pub mod theory;
+pub mod tree;
+pub mod validate;
 
 #[derive(Parser, Debug)]
 #[command(name = "randmst")]
 #[command(version = "1.0")]
 #[command(about = "Blazingly fast sampler of minimum spanning trees of a random (Euclidean) complete graph.", long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    sample: Option<Args>,
+
+    #[command(flatten)]
+    run: RunArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Compute the Euclidean minimum spanning tree of a point set read from a file
+    Emst(EmstArgs),
+
+    /// Sample a single tree of either model and export it
+    Tree(TreeArgs),
+
+    /// Run trials of one model and print the statistics of its trees
+    Sample(SampleArgs),
+
+    /// Run trials over every combination of sizes and dimensions
+    Sweep(SweepArgs),
+
+    /// Time the trials of every model on a single thread
+    Bench(BenchArgs),
+
+    /// Check the samplers and algorithms against their slow references
+    Validate(ValidateArgs),
+
+    /// Run the experiments described in a TOML or JSON file
+    Run(ExperimentArgs),
+
+    /// Combine the results files of several runs of the same experiments
+    Merge(MergeArgs),
+
+    /// Extrapolate the constants of the functionals from results at several sizes
+    Analyze(AnalyzeArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct TreeArgs {
+    #[arg(help = "Number of points in the graph.")]
+    num_points: u32,
+
+    #[arg(value_parser = clap::value_parser!(u32).range(0..=4),
+        help = "Here a `0` dimensional should be interpreted as a random complete graph.")]
+    dimension: u32,
+
+    #[arg(
+        short,
+        long,
+        default_value = "uniform",
+        help = "Point process of the Euclidean model"
+    )]
+    distribution: PointProcess,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Algorithm::Grid,
+        help = "Algorithm computing the Euclidean tree"
+    )]
+    algorithm: Algorithm,
+
+    #[arg(long, help = "Wrap the line into a circle (dimension 1 only)")]
+    torus: bool,
+
+    #[command(flatten)]
+    export: ExportArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct EmstArgs {
+    #[arg(help = "File containing one point per line (or raw f64 coordinates).")]
+    input: PathBuf,
+
+    #[arg(
+        short,
+        long,
+        help = "Input format, guessed from the extension by default"
+    )]
+    format: Option<Format>,
+
+    #[arg(
+        short,
+        long,
+        help = "Dimension of the points, required for binary input"
+    )]
+    dimension: Option<usize>,
+
+    #[arg(
+        short,
+        long,
+        help = "Write the `u v length` edges here instead of standard output"
+    )]
+    output: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Algorithm::Grid,
+        help = "Algorithm computing the Euclidean tree"
+    )]
+    algorithm: Algorithm,
+
+    #[command(flatten)]
+    export: ExportArgs,
+}
+
+/// The historical form `randmst 0 num_points num_trials dimension`, which
+/// scripts still use
+#[derive(clap::Args, Debug)]
 struct Args {
+    #[arg(help = "Ignored, kept for compatibility.")]
     _ne: u32,
+
     #[arg(help = "Number of points per graph.")]
     num_points: u32,
 
@@ -27,88 +171,779 @@ struct Args {
     #[arg(value_parser = clap::value_parser!(u32).range(0..=4),
         help = "Here a `0` dimensional should be interpreted as a random complete graph.")]
     dimension: u32,
+}
+
+#[derive(clap::Args, Debug)]
+struct SampleArgs {
+    #[arg(long, help = "Number of points per graph.")]
+    points: u32,
+
+    #[arg(long, help = "Number of trials to run.")]
+    trials: u32,
+
+    #[arg(long, value_parser = clap::value_parser!(u32).range(0..=4),
+        help = "Dimension of the points, `0` for the random complete graph.")]
+    dimension: u32,
+
+    #[command(flatten)]
+    run: RunArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct SweepArgs {
+    #[arg(
+        long,
+        value_delimiter = ',',
+        required = true,
+        help = "Numbers of points per graph (comma separated)."
+    )]
+    points: Vec<u32>,
+
+    #[arg(long, help = "Number of trials to run for every size.")]
+    trials: u32,
+
+    #[arg(long, value_delimiter = ',', required = true,
+        value_parser = clap::value_parser!(u32).range(0..=4),
+        help = "Dimensions of the points, `0` for the random complete graph (comma separated).")]
+    dimensions: Vec<u32>,
+
+    #[command(flatten)]
+    run: RunArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct BenchArgs {
+    #[arg(help = "Only time the kernels and models whose name contains this.")]
+    filter: Option<String>,
+
+    #[arg(
+        long,
+        default_value_t = 20,
+        help = "Number of timed batches of every kernel."
+    )]
+    samples: u32,
+
+    #[arg(
+        long,
+        default_value_t = 0.5,
+        help = "Seconds every kernel runs before it is timed."
+    )]
+    warmup: f64,
+
+    #[arg(long, default_value_t = 100_000, help = "Number of points per graph.")]
+    points: u32,
 
+    #[arg(
+        long,
+        default_value_t = 10,
+        help = "Number of trials timed for every model."
+    )]
+    trials: u32,
+
+    #[arg(long, value_delimiter = ',', default_value = "0,1,2,3,4",
+        value_parser = clap::value_parser!(u32).range(0..=4),
+        help = "Dimensions to time (comma separated).")]
+    dimensions: Vec<u32>,
+}
+
+#[derive(clap::Args, Debug)]
+struct ValidateArgs {
+    #[arg(
+        long,
+        default_value_t = 1000,
+        help = "Number of instances of every check."
+    )]
+    instances: u64,
+
+    #[arg(long, default_value_t = 256, value_parser = clap::value_parser!(u32).range(1..),
+        help = "Largest number of points of an instance.")]
+    max_points: u32,
+
+    #[arg(long, default_value_t = 0, help = "Seed of the first instance.")]
+    seed: u64,
+}
+
+#[derive(clap::Args, Debug)]
+struct ExperimentArgs {
+    #[arg(help = "Experiment file, JSON if it ends in `.json` and TOML otherwise.")]
+    config: PathBuf,
+
+    #[arg(
+        short,
+        long,
+        help = "Directory for the results, instead of the one named in the file"
+    )]
+    output: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Add the trials still missing to the results already in the output directory"
+    )]
+    resume: bool,
+
+    #[arg(long, help = "Number of threads, as many as cores by default")]
+    threads: Option<usize>,
+}
+
+#[derive(clap::Args, Debug)]
+struct MergeArgs {
+    #[arg(required = true, help = "The `results.json` files to combine.")]
+    inputs: Vec<PathBuf>,
+
+    #[arg(short, long, help = "Where to write the combined results.")]
+    output: PathBuf,
+
+    #[arg(long, help = "Also write the combined results as CSV.")]
+    csv: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+struct AnalyzeArgs {
+    #[arg(
+        required = true,
+        help = "Results files (`.json`) or saved output of `sweep --error`."
+    )]
+    inputs: Vec<PathBuf>,
+
+    #[arg(
+        short,
+        long,
+        default_value_t = 1,
+        help = "Number of finite-size correction terms fitted after the constant."
+    )]
+    corrections: usize,
+}
+
+/// Options of every command running trials
+#[derive(clap::Args, Debug)]
+struct RunArgs {
     #[arg(short, long, help = "Display total time and time per trial")]
     time: bool,
 
-    #[arg(short, long, help = "Run each trial in series (for debugging)")]
+    #[arg(
+        short,
+        long,
+        help = "Run the trials one at a time on a single thread (for debugging)"
+    )]
     no_parallel: bool,
 
+    #[arg(long, help = "Number of threads, as many as cores by default")]
+    threads: Option<usize>,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Schedule::Dynamic,
+        help = "How the trials are shared among the threads"
+    )]
+    schedule: Schedule,
+
     #[arg(short, long, help = "Also display the error of the result")]
     error: bool,
+
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        default_value = "1",
+        help = "Exponents α of the functionals Σ|e|^α to compute (comma separated)"
+    )]
+    alpha: Vec<f64>,
+
+    #[arg(
+        short,
+        long,
+        help = "Also display the longest edge of the tree (α → ∞)"
+    )]
+    max_edge: bool,
+
+    #[arg(
+        short,
+        long,
+        default_value = "uniform",
+        help = "Point process of the Euclidean model: uniform, gaussian:σ, ball, sphere, beta:a,b, thomas:parents,σ or matern:parents,r"
+    )]
+    distribution: PointProcess,
+
+    #[arg(
+        short,
+        long,
+        help = "Draw the number of points of each trial from Poisson(num_points)"
+    )]
+    poisson: bool,
+
+    #[arg(long, help = "Wrap the line into a circle (dimension 1 only)")]
+    torus: bool,
+
+    #[arg(
+        long,
+        help = "Seed trial `i` from `seed` and `i`, for reproducible runs"
+    )]
+    seed: Option<u64>,
+
+    #[arg(
+        short,
+        long,
+        default_value_t = euclidean::DEFAULT_GRAIN,
+        help = "Cells below which the Euclidean merge stops splitting across threads, with the split schedule"
+    )]
+    grain: usize,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Algorithm::Grid,
+        help = "Algorithm computing the Euclidean tree"
+    )]
+    algorithm: Algorithm,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Strategy::Radix,
+        help = "How Kruskal orders the edges of the Euclidean tree"
+    )]
+    kruskal: Strategy,
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    if matches!(
+        cli.command,
+        None | Some(Command::Sample(_) | Command::Sweep(_) | Command::Bench(_) | Command::Run(_))
+    ) {
+        stop_on_signal()?;
+    }
+
+    match (cli.command, cli.sample) {
+        (Some(Command::Emst(args)), _) => emst(args),
+        (Some(Command::Tree(args)), _) => tree(args),
+        (Some(Command::Sample(args)), _) => {
+            sample(args.points, args.trials, args.dimension, &args.run)
+        }
+        (Some(Command::Sweep(args)), _) => sweep(args),
+        (Some(Command::Bench(args)), _) => bench(args),
+        (Some(Command::Validate(args)), _) => validate(args),
+        (Some(Command::Run(args)), _) => run_experiment(args),
+        (Some(Command::Merge(args)), _) => merge(args),
+        (Some(Command::Analyze(args)), _) => analyze(args),
+        (None, Some(args)) => sample(args.num_points, args.num_trials, args.dimension, &cli.run),
+        (None, None) => Err(anyhow!("nothing to do, see --help")),
+    }
 }
 
-fn run_trial_zero_dim(num_points: u32, rng: impl RngCore) -> f64 {
-    complete::mst(num_points, rng)
+/// Lets SIGINT and SIGTERM stop the trials gracefully, keeping the ones
+/// done. A second signal quits at once.
+fn stop_on_signal() -> Result<()> {
+    ctrlc::set_handler(|| {
+        if runner::interrupted() {
+            std::process::exit(130);
+        }
+        eprintln!("stopping after the trials in progress, interrupt again to quit");
+        runner::interrupt();
+    })?;
+
+    Ok(())
 }
 
-fn run_trial_n_dim(num_points: u32, dimension: u32, rng: impl RngCore) -> f64 {
-    match dimension {
-        2 => euclidean::mst::<2>(num_points, rng),
-        3 => euclidean::mst::<3>(num_points, rng),
-        4 => euclidean::mst::<4>(num_points, rng),
-        _ => unimplemented!(),
+/// Marks the statistics just printed as partial when the run was cut short
+fn check_finished(finished: usize, trials: &Trials) -> Result<()> {
+    if finished < trials.num_trials as usize {
+        println!(
+            "# partial: interrupted after {finished} of {} trials",
+            trials.num_trials
+        );
+        return Err(anyhow!("interrupted"));
     }
+
+    Ok(())
 }
 
-fn run_trial(num_points: u32, dimension: u32, rng: impl RngCore) -> (f64, Duration) {
-    let start = Instant::now();
-    let mst = match dimension {
-        0 => run_trial_zero_dim(num_points, rng),
-        n => run_trial_n_dim(num_points, dimension, rng),
+fn emst(args: EmstArgs) -> Result<()> {
+    let format = args.format.unwrap_or_else(|| Format::detect(&args.input));
+    let points = PointSet::read(&args.input, format, args.dimension)?;
+    let options = Options {
+        algorithm: args.algorithm,
+        ..Options::default()
     };
-    (mst, start.elapsed())
+    let edges = points.emst(&options)?;
+    let total: f64 = edges.iter().map(|edge| edge.length).sum();
+
+    let planar = (points.dimension() == 2).then(|| points.normalized::<2>().0);
+    args.export
+        .export(&edges, points.len(), planar.as_deref())?;
+
+    match &args.output {
+        Some(path) => {
+            let file =
+                File::create(path).with_context(|| format!("creating {}", path.display()))?;
+            pointset::write_edges(BufWriter::new(file), &edges)?;
+            println!("{total}");
+        }
+        None => {
+            pointset::write_edges(BufWriter::new(stdout().lock()), &edges)?;
+            println!("# total length {total}");
+        }
+    }
+
+    Ok(())
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
-    if args.dimension == 1 {
-        return Err(anyhow!("dimension 1 is not supported!"));
-    }
-
-    // Run the trials
-    let timed_trials: Vec<(f64, Duration)> = if args.no_parallel {
-        (0..args.num_trials)
-            .into_iter()
-            .map(|_| run_trial(args.num_points, args.dimension, thread_rng()))
-            .collect()
-    } else {
-        (0..args.num_trials)
-            .into_par_iter()
-            .map(|_| run_trial(args.num_points, args.dimension, thread_rng()))
-            .collect()
+fn tree(args: TreeArgs) -> Result<()> {
+    experiment::check_functionals(&[], args.dimension, &args.distribution)?;
+    let options = Options {
+        process: args.distribution.clone(),
+        algorithm: args.algorithm,
+        torus: args.torus,
+        ..Options::default()
     };
+    model::visit(args.dimension, &options, TreeCommand(&args))?
+}
+
+/// Samples and exports a single tree of the model
+struct TreeCommand<'a>(&'a TreeArgs);
+
+impl Visitor for TreeCommand<'_> {
+    type Output = Result<()>;
+
+    fn visit<M: MstModel>(self, model: M) -> Result<()> {
+        let TreeCommand(args) = self;
+        let tree = model.tree(args.num_points, thread_rng())?;
+
+        args.export.export(
+            &tree.edges,
+            args.num_points as usize,
+            tree.planar.as_deref(),
+        )?;
+        println!(
+            "{:.6}",
+            tree.edges.iter().map(|edge| edge.length).sum::<f64>()
+        );
+
+        Ok(())
+    }
+}
+
+impl RunArgs {
+    fn options(&self) -> Options {
+        Options {
+            process: self.distribution.clone(),
+            algorithm: self.algorithm,
+            strategy: self.kruskal,
+            grain: self.schedule().grain(self.grain),
+            torus: self.torus,
+        }
+    }
+
+    fn schedule(&self) -> Schedule {
+        if self.no_parallel {
+            Schedule::Split
+        } else {
+            self.schedule
+        }
+    }
+
+    fn threads(&self) -> Option<usize> {
+        if self.no_parallel {
+            Some(1)
+        } else {
+            self.threads
+        }
+    }
+}
+
+/// Runs `f` on a pool of `threads` threads, one per core by default
+fn with_threads(threads: Option<usize>, f: impl FnOnce() -> Result<()> + Send) -> Result<()> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads.unwrap_or(0))
+        .build()?;
+    pool.install(f)
+}
+
+fn sample(num_points: u32, num_trials: u32, dimension: u32, run: &RunArgs) -> Result<()> {
+    experiment::check_functionals(&run.alpha, dimension, &run.distribution)?;
+
+    let trials = Trials {
+        num_points,
+        num_trials,
+        alphas: run.alpha.clone(),
+        poisson: run.poisson,
+        schedule: run.schedule(),
+        seed: run.seed,
+        first: 0,
+        progress: true,
+    };
+    with_threads(run.threads(), || {
+        model::visit(dimension, &run.options(), SampleCommand { trials, run })?
+    })
+}
+
+/// Runs the trials of the model and prints the statistics of the tree
+struct SampleCommand<'a> {
+    trials: Trials,
+    run: &'a RunArgs,
+}
+
+impl Visitor for SampleCommand<'_> {
+    type Output = Result<()>;
+
+    fn visit<M: MstModel>(self, model: M) -> Result<()> {
+        let SampleCommand { trials, run } = self;
+        let results = runner::run(&model, &trials)?;
+
+        // Display time calculations
+        if run.time {
+            let average_time = runner::time_per_trial(&results);
+            let mean = Duration::from_secs_f64(average_time.mean());
+            let error = Duration::from_secs_f64(average_time.error());
+            println!(
+                "time per trial: {} ± {}",
+                format!("{mean:?}").green(),
+                format!("{error:?}").red(),
+            );
+        }
+
+        let functionals = runner::functionals(&trials.alphas, &results, run.max_edge);
+        let key = Key {
+            dimension: model.dimension(),
+            points: trials.num_points,
+            functional: String::new(),
+            distribution: run.distribution.clone(),
+            torus: run.torus,
+            poisson: run.poisson,
+        };
+        report(&key, &trials.alphas, &functionals, run.error);
+
+        check_finished(results.len(), &trials)
+    }
+}
+
+/// Prints a line `mean points trials dimension` per functional of the
+/// model described by `model`, followed with `error` by the deviation from
+/// the known value of the functional, if any
+fn report(model: &Key, alphas: &[f64], functionals: &[(String, Accumulator)], error: bool) {
+    // Only label the results when more than the plain total was asked for
+    let labelled = functionals.len() > 1 || alphas.first() != Some(&1.0);
+
+    for (label, average) in functionals {
+        // Decide how to format result
+        let result = if error {
+            format!(
+                "{} ± {}",
+                format!("{:.6}", average.mean()).green(),
+                format!("{:.6}", average.error()).red()
+            )
+        } else {
+            format!("{:.6}", average.mean())
+        };
+
+        let key = Key {
+            functional: label.clone(),
+            ..model.clone()
+        };
+        let deviation = match theory::reference(&key) {
+            Some(reference) if error && average.error() > 0.0 => {
+                let deviation = reference.deviation(average.mean(), average.error());
+                let text = format!("{deviation:+.1}σ");
+                let text = if deviation.abs() < 3.0 {
+                    text.green()
+                } else {
+                    text.red()
+                };
+                format!(" ({text} from {})", reference.name)
+            }
+            _ => String::new(),
+        };
+
+        let label = if labelled {
+            format!(" {label}")
+        } else {
+            String::new()
+        };
+        println!(
+            "{} {} {} {}{}{}",
+            result, model.points, average.count, model.dimension, label, deviation
+        );
+    }
+}
+
+fn run_experiment(args: ExperimentArgs) -> Result<()> {
+    let mut experiment = Experiment::read(&args.config)?;
+    if let Some(output) = args.output {
+        experiment.output = Some(output);
+    }
+    let format = experiment::Format::detect(&args.config);
+    let mut output = experiment.create_output(format, args.resume)?;
+
+    with_threads(args.threads, || {
+        for run in &experiment.runs {
+            println!("# {}", run.name());
+            for (k, &num_points) in run.points.iter().enumerate() {
+                let done = output.done(run, num_points);
+                let trials = Trials {
+                    num_points,
+                    num_trials: (run.trials as u64).saturating_sub(done) as u32,
+                    alphas: run.alphas.clone(),
+                    poisson: run.poisson,
+                    schedule: run.schedule,
+                    seed: run.seed(k),
+                    first: done,
+                    progress: true,
+                };
+                let command = RunCommand {
+                    trials,
+                    run,
+                    output: &mut output,
+                };
+                model::visit(run.dimension, &run.options(), command)??;
+            }
+        }
+
+        Ok(())
+    })
+}
+
+/// Runs the missing trials of one size of an experiment, then records and
+/// prints the totals
+struct RunCommand<'a> {
+    trials: Trials,
+    run: &'a Run,
+    output: &'a mut Output,
+}
+
+impl Visitor for RunCommand<'_> {
+    type Output = Result<()>;
 
-    // Calculate average and variance
-    let average: MeanWithError = timed_trials.iter().map(|x| x.0).collect();
+    fn visit<M: MstModel>(self, model: M) -> Result<()> {
+        let RunCommand {
+            trials,
+            run,
+            output,
+        } = self;
+        let mut finished = 0;
+        if trials.num_trials > 0 {
+            let results = runner::run(&model, &trials)?;
+            // Only what a resumed run can carry on from
+            let results = runner::contiguous(&results, trials.first);
+            let functionals = runner::functionals(&trials.alphas, results, run.max_edge);
+            let span = Span {
+                seed: trials.seed,
+                start: trials.first,
+                end: trials.first + results.len() as u64,
+            };
+            output.record(run, trials.num_points, span, &functionals)?;
+            finished = results.len();
+        }
 
-    // Display time calculations
-    if args.time {
-        let average_time: MeanWithError = timed_trials.iter().map(|x| x.1.as_secs_f64()).collect();
-        let mean = Duration::from_secs_f64(average_time.mean());
-        let error = Duration::from_secs_f64(average_time.error());
+        let totals = output.functionals(run, trials.num_points);
+        let key = run.key(trials.num_points, String::new());
+        report(&key, &run.alphas, &totals, true);
+
+        check_finished(finished, &trials).map_err(|_| {
+            anyhow!("interrupted, pass --resume to run the trials missing from the saved results")
+        })
+    }
+}
+
+fn merge(args: MergeArgs) -> Result<()> {
+    let mut merged = Store::default();
+    for path in &args.inputs {
+        merged
+            .merge(Store::read(path)?)
+            .with_context(|| format!("merging {}", path.display()))?;
+    }
+
+    merged.write(&args.output)?;
+    if let Some(csv) = &args.csv {
+        merged.write_csv(csv)?;
+    }
+
+    for entry in &merged.entries {
+        let (key, accumulator) = (&entry.key, &entry.accumulator);
         println!(
-            "time per trial: {} ± {}",
-            format!("{mean:?}").green(),
-            format!("{error:?}").red(),
+            "{} {:.6} ± {:.6} {} {} {} {}",
+            entry.run,
+            accumulator.mean(),
+            accumulator.error(),
+            key.points,
+            accumulator.count,
+            key.dimension,
+            key.functional
         );
     }
 
-    // Decide how to format result
-    let result = if args.error {
-        format!(
-            "{} ± {}",
-            format!("{:.6}", average.mean()).green(),
-            format!("{:.6}", average.error()).red()
-        )
-    } else {
-        format!("{:.6}", average.mean())
+    Ok(())
+}
+
+fn analyze(args: AnalyzeArgs) -> Result<()> {
+    let mut results = Store::default();
+    for path in &args.inputs {
+        let store = if path.extension().is_some_and(|x| x == "json") {
+            Store::read(path)?
+        } else {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("reading {}", path.display()))?;
+            analysis::parse_report(&text).with_context(|| format!("parsing {}", path.display()))?
+        };
+        results
+            .merge(store)
+            .with_context(|| format!("merging {}", path.display()))?;
+    }
+
+    for (key, sizes) in analysis::groups(&results) {
+        let name = format!("{} {}", describe(&key), key.functional);
+        let Some(scaling) = Scaling::of(key.dimension, &key.functional) else {
+            println!("# {name}: no known finite-size form");
+            continue;
+        };
+
+        match scaling.fit(&sizes, args.corrections) {
+            Ok(fit) => {
+                let constant = fit.coefficients[0];
+                let corrections: Vec<String> = fit.coefficients[1..]
+                    .iter()
+                    .map(|b| format!("{:.4}", b / constant))
+                    .collect();
+                let quality = match fit.dof {
+                    0 => String::new(),
+                    dof => format!(", χ²/dof {:.2}", fit.chi2 / dof as f64),
+                };
+                let deviation = match theory::limit(&key) {
+                    Some(limit) => format!(
+                        ", {:+.1}σ from {}",
+                        limit.deviation(constant, fit.error(0)),
+                        limit.name
+                    ),
+                    None => String::new(),
+                };
+                println!(
+                    "{name}: {} ± {} over {} sizes, corrections [{}]{quality}{deviation}",
+                    format!("{constant:.6}").green(),
+                    format!("{:.6}", fit.error(0)).red(),
+                    sizes.len(),
+                    corrections.join(", ")
+                );
+            }
+            Err(err) => println!("# {name}: {err}"),
+        }
+    }
+
+    Ok(())
+}
+
+/// The model of a configuration, as `complete` or `dimension d` followed by
+/// what differs from uniform points of fixed size
+fn describe(key: &Key) -> String {
+    let mut name = match key.dimension {
+        0 => "complete".to_string(),
+        d => format!("dimension {d}"),
+    };
+    if key.distribution != PointProcess::Uniform {
+        name += &format!(" {}", key.distribution);
+    }
+    if key.torus {
+        name += " torus";
+    }
+    if key.poisson {
+        name += " poisson";
+    }
+
+    name
+}
+
+fn sweep(args: SweepArgs) -> Result<()> {
+    for &dimension in &args.dimensions {
+        for &num_points in &args.points {
+            sample(num_points, args.trials, dimension, &args.run)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn bench(args: BenchArgs) -> Result<()> {
+    let kernels = bench::Bench {
+        filter: args.filter.clone(),
+        warmup: Duration::from_secs_f64(args.warmup),
+        samples: args.samples,
     };
+    with_threads(Some(1), || {
+        bench::Bench::header();
+        bench::all(&kernels);
+        Ok(())
+    })?;
 
-    println!(
-        "{} {} {} {}",
-        result, args.num_points, args.num_trials, args.dimension
-    );
+    println!();
+    println!("{:<28} {:>10} {:>24}", "model", "points", "time per trial");
+    for &dimension in &args.dimensions {
+        let trials = Trials {
+            num_points: args.points,
+            num_trials: args.trials,
+            alphas: vec![1.0],
+            poisson: false,
+            schedule: Schedule::Split,
+            seed: None,
+            first: 0,
+            progress: false,
+        };
+        let command = BenchCommand {
+            trials,
+            filter: args.filter.as_deref(),
+        };
+        with_threads(Some(1), || {
+            model::visit(dimension, &Options::default(), command)?
+        })?;
+    }
 
     Ok(())
 }
+
+/// Times the trials of the model on a single thread
+struct BenchCommand<'a> {
+    trials: Trials,
+    filter: Option<&'a str>,
+}
+
+impl Visitor for BenchCommand<'_> {
+    type Output = Result<()>;
+
+    fn visit<M: MstModel>(self, model: M) -> Result<()> {
+        let BenchCommand { trials, filter } = self;
+        if filter.is_some_and(|filter| !model.name().contains(filter)) {
+            return Ok(());
+        }
+
+        let results = runner::run(&model, &trials)?;
+        let time = runner::time_per_trial(&results);
+        let (mean, error) = (
+            Duration::from_secs_f64(time.mean()),
+            Duration::from_secs_f64(time.error()),
+        );
+        println!(
+            "{:<28} {:>10} {:>24}",
+            model.name(),
+            trials.num_points,
+            format!("{mean:.2?} ± {error:.2?}")
+        );
+
+        check_finished(results.len(), &trials)
+    }
+}
+
+fn validate(args: ValidateArgs) -> Result<()> {
+    let mut failures = 0;
+    for check in validate::all(args.instances, args.max_points, args.seed) {
+        match check.failure {
+            None => println!("{} {}", "ok".green(), check.name),
+            Some(failure) => {
+                failures += 1;
+                println!("{} {}: {failure}", "FAILED".red(), check.name);
+            }
+        }
+    }
+
+    match failures {
+        0 => Ok(()),
+        n => Err(anyhow!("{n} checks failed")),
+    }
+}
@@ -0,0 +1,132 @@
+use rand::RngCore;
+
+use crate::{
+    complete,
+    error::{Error, Result},
+    euclidean::{self, Options},
+    functional::PowerSums,
+    tree::Edge,
+};
+
+/// A single sampled tree, with the points when they can be drawn
+pub struct Tree {
+    pub edges: Vec<Edge>,
+    pub planar: Option<Vec<[f64; 2]>>,
+}
+
+/// A random graph whose minimum spanning tree can be sampled
+pub trait MstModel: Sync {
+    /// Memory reused by the trials run on one thread
+    type Workspace: Default + Send;
+
+    /// Name of the model, as shown to the user
+    fn name(&self) -> String;
+
+    /// Dimension reported with the results, `0` for the complete graph
+    fn dimension(&self) -> u32;
+
+    /// Samples a tree on `size` points and accumulates `Σ|e|^α` for each of
+    /// the `alphas`, plus the longest edge
+    fn powers(
+        &self,
+        size: u32,
+        rng: impl RngCore,
+        alphas: &[f64],
+        workspace: &mut Self::Workspace,
+    ) -> Result<PowerSums>;
+
+    /// Samples a tree on `size` points and returns its edges
+    fn tree(&self, size: u32, rng: impl RngCore) -> Result<Tree>;
+}
+
+/// The complete graph with uniform weights
+pub struct Complete;
+
+impl MstModel for Complete {
+    type Workspace = complete::Workspace;
+
+    fn name(&self) -> String {
+        "complete graph".to_string()
+    }
+
+    fn dimension(&self) -> u32 {
+        0
+    }
+
+    fn powers(
+        &self,
+        size: u32,
+        rng: impl RngCore,
+        alphas: &[f64],
+        workspace: &mut Self::Workspace,
+    ) -> Result<PowerSums> {
+        complete::mst_powers(size, rng, alphas, workspace)
+    }
+
+    fn tree(&self, size: u32, rng: impl RngCore) -> Result<Tree> {
+        Ok(Tree {
+            edges: complete::mst_edges(size, rng)?,
+            planar: None,
+        })
+    }
+}
+
+/// Points of `[0, 1)^D` joined by their distances
+pub struct Euclidean<const D: usize> {
+    pub options: Options,
+}
+
+impl<const D: usize> MstModel for Euclidean<D>
+where
+    euclidean::Hypercube<D>: rand_distr::Distribution<euclidean::Point<D>>,
+    euclidean::Point<D>: euclidean::Morton,
+{
+    type Workspace = euclidean::Workspace<D>;
+
+    fn name(&self) -> String {
+        format!("{} points in dimension {D}", self.options.process)
+    }
+
+    fn dimension(&self) -> u32 {
+        D as u32
+    }
+
+    fn powers(
+        &self,
+        size: u32,
+        rng: impl RngCore,
+        alphas: &[f64],
+        workspace: &mut Self::Workspace,
+    ) -> Result<PowerSums> {
+        euclidean::mst_powers::<D>(size, rng, &self.options, alphas, workspace)
+    }
+
+    fn tree(&self, size: u32, rng: impl RngCore) -> Result<Tree> {
+        let points = euclidean::sample_points::<D>(size, rng, &self.options.process);
+        Ok(Tree {
+            edges: euclidean::emst(&points, &self.options)?,
+            planar: (D == 2).then(|| points.iter().map(|p| [p[0], p[1]]).collect()),
+        })
+    }
+}
+
+/// Something to do with a model, whichever it turns out to be
+pub trait Visitor {
+    type Output;
+
+    fn visit<M: MstModel>(self, model: M) -> Self::Output;
+}
+
+/// Hands the model living in `dimension` to `visitor`, where `0` stands for
+/// the complete graph
+pub fn visit<V: Visitor>(dimension: u32, options: &Options, visitor: V) -> Result<V::Output> {
+    let options = options.clone();
+    Ok(match dimension {
+        0 => visitor.visit(Complete),
+        1 => visitor.visit(Euclidean::<1> { options }),
+        2 => visitor.visit(Euclidean::<2> { options }),
+        3 => visitor.visit(Euclidean::<3> { options }),
+        4 => visitor.visit(Euclidean::<4> { options }),
+        d => return Err(Error::Dimension(d)),
+    })
+}
@@ -0,0 +1,308 @@
+use std::{fs, io::Write, path::Path};
+
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+
+use crate::{error::Error, euclidean, tree::Edge};
+
+/// On-disk layouts of a point set
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Comma separated coordinates, one point per line, optional header
+    Csv,
+    /// Whitespace separated coordinates, one point per line, `#` comments
+    Text,
+    /// Raw little-endian `f64` coordinates, point after point
+    Binary,
+}
+
+impl Format {
+    /// Guesses the format from the file extension
+    pub fn detect(path: &Path) -> Self {
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("csv") => Self::Csv,
+            Some("bin" | "f64" | "raw") => Self::Binary,
+            _ => Self::Text,
+        }
+    }
+}
+
+/// Points of a dimension only known at runtime, stored point after point
+#[derive(Clone, Debug, PartialEq)]
+pub struct PointSet {
+    dimension: usize,
+    coords: Vec<f64>,
+}
+
+impl PointSet {
+    /// Reads a point set, `dimension` is required for binary files and
+    /// checked against the number of columns otherwise
+    pub fn read(path: &Path, format: Format, dimension: Option<usize>) -> Result<Self> {
+        let points = match format {
+            Format::Binary => {
+                let dimension =
+                    dimension.ok_or_else(|| anyhow!("binary input needs a dimension"))?;
+                let bytes =
+                    fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+                Self::parse_binary(&bytes, dimension)?
+            }
+            Format::Csv | Format::Text => {
+                let text = fs::read_to_string(path)
+                    .with_context(|| format!("reading {}", path.display()))?;
+                let delimiter = (format == Format::Csv).then_some(',');
+                Self::parse_delimited(&text, delimiter)?
+            }
+        };
+
+        match dimension {
+            Some(dimension) if dimension != points.dimension => Err(anyhow!(
+                "expected {dimension} dimensional points, found {}",
+                points.dimension
+            )),
+            _ => Ok(points),
+        }
+    }
+
+    /// Parses one point per line, splitting on `delimiter` (or whitespace).
+    /// Blank lines, `#` comments and a non-numeric first line, the header,
+    /// are skipped.
+    pub fn parse_delimited(text: &str, delimiter: Option<char>) -> Result<Self> {
+        let mut dimension = None;
+        let mut coords = Vec::new();
+        let mut header = true;
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let first = std::mem::replace(&mut header, false);
+
+            let row: Result<Vec<f64>, _> = match delimiter {
+                Some(delimiter) => line.split(delimiter).map(|x| x.trim().parse()).collect(),
+                None => line.split_whitespace().map(|x| x.parse()).collect(),
+            };
+
+            let row = match row {
+                Ok(row) => row,
+                Err(_) if first => continue,
+                Err(err) => return Err(anyhow!("line {}: {err}", number + 1)),
+            };
+
+            let expected = *dimension.get_or_insert(row.len());
+            if expected != row.len() {
+                return Err(anyhow!(
+                    "line {}: expected {expected} coordinates",
+                    number + 1
+                ));
+            }
+
+            coords.extend(row);
+        }
+
+        Ok(Self {
+            dimension: dimension.ok_or_else(|| anyhow!("no points in input"))?,
+            coords,
+        })
+    }
+
+    pub fn parse_binary(bytes: &[u8], dimension: usize) -> Result<Self> {
+        if dimension == 0 || !bytes.len().is_multiple_of(8 * dimension) {
+            return Err(anyhow!(
+                "{} bytes is not a whole number of {dimension} dimensional points",
+                bytes.len()
+            ));
+        }
+
+        Ok(Self {
+            dimension,
+            coords: bytes
+                .chunks_exact(8)
+                .map(|x| f64::from_le_bytes(x.try_into().unwrap()))
+                .collect(),
+        })
+    }
+
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    pub fn len(&self) -> usize {
+        self.coords.len() / self.dimension
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.coords.is_empty()
+    }
+
+    pub fn point(&self, i: usize) -> &[f64] {
+        &self.coords[i * self.dimension..(i + 1) * self.dimension]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &[f64]> {
+        self.coords.chunks_exact(self.dimension)
+    }
+
+    /// Maps the points into `[0, 1)^D` by translating the bounding box to the
+    /// origin and scaling every axis by the same factor, which is returned so
+    /// that lengths can be converted back
+    pub fn normalized<const D: usize>(&self) -> (Vec<[f64; D]>, f64) {
+        assert_eq!(self.dimension, D);
+
+        let mut min = [f64::INFINITY; D];
+        let mut max = [f64::NEG_INFINITY; D];
+        for point in self.iter() {
+            for i in 0..D {
+                min[i] = min[i].min(point[i]);
+                max[i] = max[i].max(point[i]);
+            }
+        }
+
+        // Stay strictly below 1 so that the fixed point conversion can't wrap
+        let extent = (0..D).map(|i| max[i] - min[i]).fold(0.0, f64::max);
+        let scale = match extent > 0.0 {
+            true => (1.0 - f64::EPSILON * 8.0) / extent,
+            false => 1.0,
+        };
+
+        let points = self
+            .iter()
+            .map(|point| std::array::from_fn(|i| (point[i] - min[i]) * scale))
+            .collect();
+
+        (points, scale)
+    }
+
+    /// Computes the Euclidean minimum spanning tree, with lengths in the
+    /// units of the input
+    pub fn emst(&self, options: &euclidean::Options) -> Result<Vec<Edge>> {
+        if self.coords.iter().any(|x| !x.is_finite()) {
+            return Err(anyhow!("coordinates must be finite"));
+        }
+
+        match self.dimension {
+            1 => self.emst_dim::<1>(options),
+            2 => self.emst_dim::<2>(options),
+            3 => self.emst_dim::<3>(options),
+            4 => self.emst_dim::<4>(options),
+            d => Err(Error::Dimension(d as u32).into()),
+        }
+    }
+
+    fn emst_dim<const D: usize>(&self, options: &euclidean::Options) -> Result<Vec<Edge>>
+    where
+        euclidean::Point<D>: euclidean::Morton,
+    {
+        let (points, scale) = self.normalized::<D>();
+        let mut edges = euclidean::emst(&points, options)?;
+        // The side of the torus is 1 in the normalized units
+        let period = options.torus.then_some(1.0 / scale);
+        for edge in &mut edges {
+            edge.length = euclidean::distance(
+                self.point(edge.u as usize),
+                self.point(edge.v as usize),
+                period,
+            );
+        }
+
+        Ok(edges)
+    }
+}
+
+/// Writes one `u v length` line per edge
+pub fn write_edges(mut out: impl Write, edges: &[Edge]) -> Result<()> {
+    for edge in edges {
+        writeln!(out, "{} {} {}", edge.u, edge.v, edge.length)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PointSet;
+    use crate::{
+        error::Error,
+        euclidean::{Algorithm, Options},
+    };
+
+    #[test]
+    fn parse_csv() {
+        let points = PointSet::parse_delimited("x,y\n0,1\n2.5, 3\n\n", Some(',')).unwrap();
+        assert_eq!(points.dimension(), 2);
+        assert_eq!(points.len(), 2);
+        assert!(PointSet::parse_delimited("0,1\n2\n", Some(',')).is_err());
+
+        // The header may follow comments, but only comes once
+        let text = "# sample\n\nx y\n1 2\n";
+        assert_eq!(PointSet::parse_delimited(text, None).unwrap().len(), 1);
+        assert!(PointSet::parse_delimited("x y\nz w\n1 2\n", None).is_err());
+    }
+
+    #[test]
+    fn parse_binary() {
+        let bytes: Vec<u8> = [1.0_f64, 2.0, 3.0, 4.0, 5.0, 6.0]
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect();
+        assert_eq!(PointSet::parse_binary(&bytes, 3).unwrap().len(), 2);
+        assert!(PointSet::parse_binary(&bytes, 4).is_err());
+    }
+
+    #[test]
+    fn emst_of_grid() {
+        // A 10 by 10 grid with spacing 3, the tree is 99 edges of length 3
+        let text: String = (0..100)
+            .map(|i| format!("{} {}\n", 3 * (i % 10), 3 * (i / 10)))
+            .collect();
+        let points = PointSet::parse_delimited(&text, None).unwrap();
+        let edges = points.emst(&Default::default()).unwrap();
+
+        assert_eq!(edges.len(), 99);
+        let total: f64 = edges.iter().map(|edge| edge.length).sum();
+        assert!((total - 297.0).abs() < 1e-6);
+        // Measured on the input coordinates, not the fixed point ones
+        assert!(edges.iter().all(|edge| edge.length == 3.0));
+    }
+
+    #[test]
+    fn emst_of_few_points() {
+        let points = PointSet::parse_delimited("0 0 0\n0 0 2\n0 1 2\n", None).unwrap();
+        let edges = points.emst(&Default::default()).unwrap();
+        let total: f64 = edges.iter().map(|edge| edge.length).sum();
+        assert!((total - 3.0).abs() < 1e-9);
+
+        let single = PointSet::parse_delimited("1 1\n", None).unwrap();
+        assert!(single.emst(&Default::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn emst_errors() {
+        let error = |points: &PointSet, options| {
+            points
+                .emst(&options)
+                .unwrap_err()
+                .downcast::<Error>()
+                .unwrap()
+        };
+
+        let empty = PointSet::parse_binary(&[], 2).unwrap();
+        assert_eq!(error(&empty, Options::default()), Error::NoPoints);
+
+        let space = PointSet::parse_delimited("0 0 0\n1 1 1\n", None).unwrap();
+        let delaunay = Options {
+            algorithm: Algorithm::Delaunay,
+            ..Options::default()
+        };
+        assert_eq!(
+            error(&space, delaunay),
+            Error::PlanarOnly {
+                algorithm: "delaunay",
+                dimension: 3
+            }
+        );
+
+        let high = PointSet::parse_delimited("0 0 0 0 0\n1 1 1 1 1\n", None).unwrap();
+        assert_eq!(error(&high, Options::default()), Error::Dimension(5));
+    }
+}
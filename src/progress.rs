@@ -0,0 +1,132 @@
+use std::{
+    io::{stderr, Write},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::statistics::Accumulator;
+
+/// Time between two redraws of the progress line
+const INTERVAL: Duration = Duration::from_millis(250);
+
+/// A progress line on stderr, fed by the workers as their trials finish.
+/// Whichever worker finds the line out of date redraws it, so there is no
+/// thread to wake up. Each thread of the pool accumulates into a slot of
+/// its own, which only the redraws ever share, so the workers don't wait on
+/// each other however short the trials.
+pub struct Progress {
+    total: u64,
+    /// Name of the functional whose running mean is shown
+    label: String,
+    start: Instant,
+    /// One per thread of the pool, and a last one for any other thread
+    slots: Vec<Slot>,
+    /// Milliseconds from `start` to the last redraw
+    drawn: AtomicU64,
+}
+
+/// Kept on a cache line of its own, so that the threads don't contend for
+/// the slots of their neighbors
+#[derive(Default)]
+#[repr(align(128))]
+struct Slot(Mutex<Accumulator>);
+
+impl Progress {
+    pub fn new(total: u64, label: String) -> Self {
+        Self {
+            total,
+            label,
+            start: Instant::now(),
+            slots: (0..=rayon::current_num_threads())
+                .map(|_| Slot::default())
+                .collect(),
+            drawn: AtomicU64::new(0),
+        }
+    }
+
+    /// Counts a finished trial and the value of its functional
+    pub fn record(&self, value: f64) {
+        let slot = rayon::current_thread_index()
+            .filter(|&index| index < self.slots.len() - 1)
+            .unwrap_or(self.slots.len() - 1);
+        self.slots[slot].0.lock().unwrap().add(value);
+
+        let now = self.start.elapsed().as_millis() as u64;
+        let drawn = self.drawn.load(Ordering::Relaxed);
+        if now >= drawn + INTERVAL.as_millis() as u64
+            && self
+                .drawn
+                .compare_exchange(drawn, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            eprint!("\r\x1b[K{}", self.line());
+            stderr().flush().ok();
+        }
+    }
+
+    /// Erases the line, leaving the terminal to the results
+    pub fn finish(&self) {
+        eprint!("\r\x1b[K");
+        stderr().flush().ok();
+    }
+
+    fn line(&self) -> String {
+        let mut statistics = Accumulator::new();
+        for slot in &self.slots {
+            statistics.merge(&slot.0.lock().unwrap());
+        }
+        let done = statistics.count;
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let rate = done as f64 / elapsed;
+        let eta = self.total.saturating_sub(done) as f64 / rate;
+
+        format!(
+            "{done}/{} trials, {rate:.1}/s, eta {}, {} {:.6} ± {:.6}",
+            self.total,
+            format_duration(eta),
+            self.label,
+            statistics.mean(),
+            statistics.error()
+        )
+    }
+}
+
+/// Whole seconds, as `1h02m03s`, `2m03s` or `3s`
+fn format_duration(seconds: f64) -> String {
+    if !seconds.is_finite() {
+        return "?".to_string();
+    }
+
+    let seconds = seconds.round() as u64;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    match (hours, minutes) {
+        (0, 0) => format!("{seconds}s"),
+        (0, _) => format!("{minutes}m{seconds:02}s"),
+        _ => format!("{hours}h{minutes:02}m{seconds:02}s"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_duration, Progress};
+
+    #[test]
+    fn line() {
+        let progress = Progress::new(10, "alpha=1".to_string());
+        for value in [1.0, 2.0, 3.0] {
+            progress.record(value);
+        }
+
+        let line = progress.line();
+        assert!(line.starts_with("3/10 trials, "), "{line}");
+        assert!(line.ends_with("alpha=1 2.000000 ± 0.577350"), "{line}");
+
+        assert_eq!(format_duration(3.4), "3s");
+        assert_eq!(format_duration(123.0), "2m03s");
+        assert_eq!(format_duration(3723.0), "1h02m03s");
+        assert_eq!(format_duration(f64::INFINITY), "?");
+    }
+}
@@ -0,0 +1,416 @@
+use std::{
+    io::{stderr, IsTerminal},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use clap::ValueEnum;
+use rand::{rngs::SmallRng, thread_rng, RngCore, SeedableRng};
+use rand_distr::{Distribution, Poisson};
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Result, functional::PowerSums, model::MstModel, progress::Progress,
+    statistics::Accumulator,
+};
+
+/// How the trials of a run are drawn
+#[derive(Clone, Debug)]
+pub struct Trials {
+    pub num_points: u32,
+    pub num_trials: u32,
+    /// Exponents of the functionals `Σ|e|^α`
+    pub alphas: Vec<f64>,
+    /// Draws the number of points of each trial from `Poisson(num_points)`
+    pub poisson: bool,
+    pub schedule: Schedule,
+    /// Makes the run reproducible, trial `i` drawing from a generator
+    /// seeded with `trial_seed(seed, i)`
+    pub seed: Option<u64>,
+    /// Index of the first trial, so that a resumed run carries on with
+    /// fresh seeds
+    pub first: u64,
+    /// Shows the progress of the run on stderr, when it is a terminal
+    pub progress: bool,
+}
+
+/// How the trials of a run are shared among the threads of the pool
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Schedule {
+    /// The threads take trials one by one, stealing from each other
+    #[default]
+    Dynamic,
+    /// Each thread runs its own contiguous block of trials, reusing its
+    /// workspace from one trial to the next
+    Chunks,
+    /// One trial at a time, the threads sharing the work within it. Only the
+    /// merges of the Euclidean grid split across threads, see `grain`.
+    Split,
+}
+
+impl Schedule {
+    /// The grain of the Euclidean merges under the schedule: only `Split`
+    /// shares a trial among the threads, the others keep each trial on the
+    /// thread running it
+    pub fn grain(self, grain: usize) -> usize {
+        match self {
+            Self::Split => grain,
+            Self::Dynamic | Self::Chunks => usize::MAX,
+        }
+    }
+}
+
+/// Set when the user asks the runs to stop
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Stops the runs gracefully: the trials in progress finish, and the ones
+/// not started yet are skipped
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::Relaxed);
+}
+
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// Outcome of a single trial
+#[derive(Clone, Debug)]
+pub struct Trial {
+    /// Index of the trial in the run, counting from `Trials::first`
+    pub index: u64,
+    pub sums: PowerSums,
+    pub time: Duration,
+}
+
+/// The number of points in a trial, either exactly `num_points` or
+/// Poisson distributed with that mean
+fn trial_size(num_points: u32, poisson: bool, rng: &mut impl RngCore) -> u32 {
+    if poisson && num_points > 0 {
+        Poisson::new(num_points as f64).unwrap().sample(rng) as u32
+    } else {
+        num_points
+    }
+}
+
+fn run_trial<M: MstModel>(
+    model: &M,
+    trials: &Trials,
+    workspace: &mut M::Workspace,
+    index: u64,
+    mut rng: impl RngCore,
+) -> Result<Trial> {
+    let start = Instant::now();
+    let size = trial_size(trials.num_points, trials.poisson, &mut rng);
+    let sums = match size {
+        // A Poisson draw may leave the trial without points
+        0 if trials.poisson => PowerSums::new(&trials.alphas),
+        size => model.powers(size, rng, &trials.alphas, workspace)?,
+    };
+
+    Ok(Trial {
+        index,
+        sums,
+        time: start.elapsed(),
+    })
+}
+
+/// Seed of the trial numbered `index` of a run seeded with `seed`, mixing
+/// both with SplitMix64's finalizer so that runs of nearby seeds share no
+/// trials, which `seed + index` would
+fn trial_seed(seed: u64, index: u64) -> u64 {
+    let mix = |mut z: u64| {
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    };
+    mix(mix(seed.wrapping_add(0x9E37_79B9_7F4A_7C15)) ^ index)
+}
+
+/// Runs the trial numbered `index` with the generator it is due
+fn run_indexed<M: MstModel>(
+    model: &M,
+    trials: &Trials,
+    workspace: &mut M::Workspace,
+    index: u32,
+) -> Result<Trial> {
+    let index = trials.first + index as u64;
+    match trials.seed {
+        Some(seed) => {
+            let rng = SmallRng::seed_from_u64(trial_seed(seed, index));
+            run_trial(model, trials, workspace, index, rng)
+        }
+        None => run_trial(model, trials, workspace, index, thread_rng()),
+    }
+}
+
+/// Runs every trial on `model`, each thread reusing its own workspace.
+/// Once interrupted, only the trials already started are returned, in
+/// order of index.
+pub fn run<M: MstModel>(model: &M, trials: &Trials) -> Result<Vec<Trial>> {
+    run_until(model, trials, &INTERRUPTED)
+}
+
+/// Same as `run`, skipping the trials not started yet once `stop` is set
+fn run_until<M: MstModel>(model: &M, trials: &Trials, stop: &AtomicBool) -> Result<Vec<Trial>> {
+    let stopped = || stop.load(Ordering::Relaxed);
+    let progress =
+        (trials.progress && !trials.alphas.is_empty() && stderr().is_terminal()).then(|| {
+            let label = labels(&trials.alphas, false).swap_remove(0);
+            Progress::new(trials.num_trials as u64, label)
+        });
+    let run_one = |workspace: &mut M::Workspace, index| {
+        let result = run_indexed(model, trials, workspace, index);
+        if let (Some(progress), Ok(trial)) = (&progress, &result) {
+            progress.record(trial.sums.sums()[0]);
+        }
+        result
+    };
+
+    let run_block = |indices: std::ops::Range<u32>| {
+        let mut workspace = M::Workspace::default();
+        indices
+            .take_while(|_| !stopped())
+            .map(|index| run_one(&mut workspace, index))
+            .collect::<Vec<_>>()
+    };
+
+    let results = match trials.schedule {
+        Schedule::Dynamic => (0..trials.num_trials)
+            .into_par_iter()
+            .map_init(M::Workspace::default, |workspace, index| {
+                (!stopped()).then(|| run_one(workspace, index))
+            })
+            .flatten()
+            .collect(),
+        Schedule::Chunks => {
+            let threads = rayon::current_num_threads() as u32;
+            let size = trials.num_trials.div_ceil(threads).max(1);
+            let starts: Vec<u32> = (0..trials.num_trials).step_by(size as usize).collect();
+            starts
+                .into_par_iter()
+                .flat_map_iter(|start| {
+                    run_block(start..start.saturating_add(size).min(trials.num_trials))
+                })
+                .collect()
+        }
+        Schedule::Split => run_block(0..trials.num_trials).into_iter().collect(),
+    };
+
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+    results
+}
+
+/// The trials of an interrupted run up to the first one skipped, which
+/// together with the trials before `first` use every seed once: resuming
+/// from there neither repeats nor skips any
+pub fn contiguous(trials: &[Trial], first: u64) -> &[Trial] {
+    let end = trials
+        .iter()
+        .zip(first..)
+        .take_while(|(trial, index)| trial.index == *index)
+        .count();
+    &trials[..end]
+}
+
+/// Names of the functionals, `alpha=<α>` for each exponent and `max` for
+/// the longest edge when `max_edge` is set
+pub fn labels(alphas: &[f64], max_edge: bool) -> Vec<String> {
+    let mut labels: Vec<String> = alphas
+        .iter()
+        .map(|alpha| format!("alpha={alpha}"))
+        .collect();
+    if max_edge {
+        labels.push("max".to_string());
+    }
+
+    labels
+}
+
+/// Statistics of each functional over the trials, in the order of `labels`
+pub fn functionals(alphas: &[f64], trials: &[Trial], max_edge: bool) -> Vec<(String, Accumulator)> {
+    labels(alphas, max_edge)
+        .into_iter()
+        .enumerate()
+        .map(|(i, label)| {
+            let values = trials.iter().map(|trial| match trial.sums.sums().get(i) {
+                Some(&sum) => sum,
+                None => trial.sums.max(),
+            });
+            (label, values.collect())
+        })
+        .collect()
+}
+
+/// Mean and error of the time per trial
+pub fn time_per_trial(trials: &[Trial]) -> Accumulator {
+    trials
+        .iter()
+        .map(|trial| trial.time.as_secs_f64())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use super::{contiguous, functionals, run, run_until, Schedule, Trials};
+    use crate::{
+        euclidean::Options,
+        model::{Complete, Euclidean},
+    };
+
+    fn trials(num_points: u32, poisson: bool) -> Trials {
+        Trials {
+            num_points,
+            num_trials: 4000,
+            alphas: vec![1.0, 2.0],
+            poisson,
+            schedule: Schedule::Dynamic,
+            seed: None,
+            first: 0,
+            progress: false,
+        }
+    }
+
+    #[test]
+    fn functionals_of_trials() {
+        let model = Euclidean::<1> {
+            options: Options::default(),
+        };
+        let trials = Trials {
+            num_trials: 5,
+            seed: Some(1),
+            ..trials(50, false)
+        };
+        let results = run(&model, &trials).unwrap();
+        let indices: Vec<u64> = results.iter().map(|trial| trial.index).collect();
+        assert_eq!(indices, [0, 1, 2, 3, 4]);
+
+        let functionals = functionals(&[1.0, 2.0], &results, true);
+        let labels: Vec<&str> = functionals
+            .iter()
+            .map(|(label, _)| label.as_str())
+            .collect();
+        assert_eq!(labels, ["alpha=1", "alpha=2", "max"]);
+        assert!(functionals.iter().all(|(_, sample)| sample.count == 5));
+
+        // The functionals follow the sums, then the longest edge
+        let expected = [
+            results.iter().map(|trial| trial.sums.sums()[1]).collect(),
+            results.iter().map(|trial| trial.sums.max()).collect(),
+        ];
+        assert_eq!([functionals[1].1, functionals[2].1], expected);
+    }
+
+    #[test]
+    fn poisson_sizes() {
+        // With a single point on average, about a third of the trials draw
+        // none and still count as an empty tree
+        let results = run(&Complete, &trials(1, true)).unwrap();
+        let functionals = functionals(&[1.0, 2.0], &results, true);
+        assert_eq!(functionals.len(), 3);
+        assert!(functionals[0].1.mean() > 0.0);
+        assert!(run(&Complete, &trials(0, false)).is_err());
+    }
+
+    #[test]
+    fn seeded() {
+        let seeded = Trials {
+            seed: Some(7),
+            ..trials(100, true)
+        };
+        let split = Trials {
+            schedule: Schedule::Split,
+            ..seeded.clone()
+        };
+        let chunks = Trials {
+            schedule: Schedule::Chunks,
+            ..seeded.clone()
+        };
+
+        let model = Euclidean::<2> {
+            options: Options::default(),
+        };
+        let sums = |trials: &Trials| -> Vec<Vec<f64>> {
+            let results = run(&model, trials).unwrap();
+            results
+                .iter()
+                .map(|trial| trial.sums.sums().to_vec())
+                .collect()
+        };
+        assert_eq!(sums(&seeded), sums(&split));
+        assert_eq!(sums(&seeded), sums(&chunks));
+
+        // The next seed draws other trials, not the same ones shifted by one
+        let next = Trials {
+            seed: Some(8),
+            ..seeded.clone()
+        };
+        let (seeded, next) = (sums(&seeded), sums(&next));
+        assert!(seeded[1..].iter().all(|trial| !next.contains(trial)));
+
+        // Only the split schedule shares a trial among threads
+        assert_eq!(Schedule::Split.grain(16), 16);
+        assert_eq!(Schedule::Chunks.grain(16), usize::MAX);
+    }
+
+    #[test]
+    fn resumed() {
+        // Six trials, then the four after them, make up the first ten
+        let model = Complete;
+        let whole = Trials {
+            num_trials: 10,
+            seed: Some(11),
+            ..trials(30, false)
+        };
+        let statistics =
+            |trials: &Trials| functionals(&[1.0], &run(&model, trials).unwrap(), false);
+
+        let mut resumed = statistics(&Trials {
+            num_trials: 6,
+            ..whole.clone()
+        });
+        let rest = statistics(&Trials {
+            num_trials: 4,
+            first: 6,
+            ..whole.clone()
+        });
+        resumed[0].1.merge(&rest[0].1);
+
+        let whole = &statistics(&whole)[0].1;
+        assert_eq!(resumed[0].1.count, 10);
+        assert!((resumed[0].1.mean - whole.mean).abs() < 1e-12);
+        assert!((resumed[0].1.m2 - whole.m2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn interrupted() {
+        let model = Complete;
+        let seeded = Trials {
+            seed: Some(3),
+            first: 20,
+            ..trials(30, false)
+        };
+        for schedule in [Schedule::Dynamic, Schedule::Chunks, Schedule::Split] {
+            let trials = Trials {
+                schedule,
+                ..seeded.clone()
+            };
+            assert!(run_until(&model, &trials, &AtomicBool::new(true))
+                .unwrap()
+                .is_empty());
+
+            let mut results = run_until(&model, &trials, &AtomicBool::new(false)).unwrap();
+            assert_eq!(results.len(), 4000);
+            assert_eq!(contiguous(&results, 20).len(), 4000);
+
+            // A gap left by trials skipped cuts the results short
+            results.remove(10);
+            assert_eq!(contiguous(&results, 20).len(), 10);
+        }
+    }
+}
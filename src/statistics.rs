@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+
+/// Running count, mean and sum of squared deviations (`M2`) of a sample,
+/// updated one value at a time with Welford's method. Two accumulators
+/// merge into the one of the union of their samples, so the statistics of
+/// separate runs combine exactly as if they had been a single run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Accumulator {
+    pub count: u64,
+    pub mean: f64,
+    pub m2: f64,
+}
+
+impl Accumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The accumulator of `count` values with the given mean and standard
+    /// error of the mean
+    pub fn from_error(count: u64, mean: f64, error: f64) -> Self {
+        let n = count as f64;
+        Self {
+            count,
+            mean,
+            m2: error * error * n * (n - 1.0),
+        }
+    }
+
+    pub fn add(&mut self, x: f64) {
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    /// Adds the sample of `other`, following Chan et al.
+    pub fn merge(&mut self, other: &Self) {
+        if other.count == 0 {
+            return;
+        }
+
+        let count = self.count + other.count;
+        let delta = other.mean - self.mean;
+        let weight = other.count as f64 / count as f64;
+        self.m2 += other.m2 + delta * delta * self.count as f64 * weight;
+        self.mean += delta * weight;
+        self.count = count;
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Unbiased estimate of the variance of the sample
+    pub fn sample_variance(&self) -> f64 {
+        match self.count {
+            0 | 1 => 0.0,
+            n => self.m2 / (n - 1) as f64,
+        }
+    }
+
+    /// Standard error of the mean
+    pub fn error(&self) -> f64 {
+        match self.count {
+            0 => 0.0,
+            n => (self.sample_variance() / n as f64).sqrt(),
+        }
+    }
+}
+
+impl FromIterator<f64> for Accumulator {
+    fn from_iter<T: IntoIterator<Item = f64>>(iter: T) -> Self {
+        let mut accumulator = Self::new();
+        for x in iter {
+            accumulator.add(x);
+        }
+
+        accumulator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use average::{Estimate, MeanWithError};
+
+    use super::Accumulator;
+
+    #[test]
+    fn matches_average() {
+        let values: Vec<f64> = (0..100).map(|i| ((i * 37) % 101) as f64 / 7.0).collect();
+        let ours: Accumulator = values.iter().copied().collect();
+        let theirs: MeanWithError = values.iter().copied().collect();
+
+        assert_eq!(ours.count, 100);
+        assert!((ours.mean() - theirs.mean()).abs() < 1e-12);
+        assert!((ours.error() - theirs.error()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn merge() {
+        let values: Vec<f64> = (0..50).map(|i| (i as f64).sin()).collect();
+        let whole: Accumulator = values.iter().copied().collect();
+
+        for split in [0, 1, 17, 50] {
+            let mut left: Accumulator = values[..split].iter().copied().collect();
+            let right: Accumulator = values[split..].iter().copied().collect();
+            left.merge(&right);
+
+            assert_eq!(left.count, whole.count);
+            assert!((left.mean - whole.mean).abs() < 1e-12);
+            assert!((left.m2 - whole.m2).abs() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use std::{fs, io::Write, path::Path};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{euclidean::PointProcess, statistics::Accumulator};
+
+/// What makes trials interchangeable: the accumulators of equal keys
+/// sample the same distribution and can be merged, whichever algorithm,
+/// seed or machine produced them
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Key {
+    pub dimension: u32,
+    pub points: u32,
+    pub functional: String,
+    pub distribution: PointProcess,
+    pub torus: bool,
+    pub poisson: bool,
+}
+
+/// The trials `start..end` of a run, as numbered by the runner. Seeded
+/// trials are reproducible, so the same ones must never be counted twice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Span {
+    pub seed: Option<u64>,
+    pub start: u64,
+    pub end: u64,
+}
+
+impl Span {
+    /// Whether both hold some trial drawn from the same generator
+    fn overlaps(&self, other: &Span) -> bool {
+        self.seed.is_some()
+            && self.seed == other.seed
+            && self.start < other.end
+            && other.start < self.end
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    /// Name of the run which first produced the entry
+    pub run: String,
+    #[serde(flatten)]
+    pub key: Key,
+    /// The trials behind the statistics, unknown for results read from a
+    /// report
+    #[serde(default)]
+    pub trials: Vec<Span>,
+    #[serde(flatten)]
+    pub accumulator: Accumulator,
+}
+
+/// Accumulated statistics of every configuration ever run, as stored in
+/// `results.json`
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Store {
+    pub entries: Vec<Entry>,
+}
+
+impl Store {
+    pub fn read(path: &Path) -> Result<Self> {
+        let text =
+            fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        serde_json::from_str(&text).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    /// Replaces the file at once, so an interruption never leaves half of
+    /// it behind
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let partial = path.with_extension("json.partial");
+        fs::write(&partial, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("writing {}", partial.display()))?;
+        fs::rename(&partial, path).with_context(|| format!("writing {}", path.display()))
+    }
+
+    /// One line per entry, for spreadsheets and plotting scripts
+    pub fn write_csv(&self, path: &Path) -> Result<()> {
+        let mut out = Vec::new();
+        writeln!(out, "run,dimension,points,trials,functional,mean,error")?;
+        for Entry {
+            run,
+            key,
+            accumulator,
+            ..
+        } in &self.entries
+        {
+            writeln!(
+                out,
+                "{run},{},{},{},{},{},{}",
+                key.dimension,
+                key.points,
+                accumulator.count,
+                key.functional,
+                accumulator.mean(),
+                accumulator.error()
+            )?;
+        }
+
+        fs::write(path, out).with_context(|| format!("writing {}", path.display()))
+    }
+
+    pub fn get(&self, key: &Key) -> Option<&Accumulator> {
+        self.entries
+            .iter()
+            .find(|entry| entry.key == *key)
+            .map(|entry| &entry.accumulator)
+    }
+
+    /// Merges `accumulator`, the statistics of the trials `spans`, into the
+    /// entry of `key`, which is created if needed. Trials already in the
+    /// entry are an error, as they would be counted twice.
+    pub fn add(
+        &mut self,
+        run: &str,
+        key: Key,
+        spans: &[Span],
+        accumulator: &Accumulator,
+    ) -> Result<()> {
+        let index = match self.entries.iter().position(|entry| entry.key == key) {
+            Some(index) => index,
+            None => {
+                self.entries.push(Entry {
+                    run: run.to_string(),
+                    key,
+                    trials: Vec::new(),
+                    accumulator: Accumulator::new(),
+                });
+                self.entries.len() - 1
+            }
+        };
+        let entry = &mut self.entries[index];
+
+        for span in spans {
+            if let Some(repeated) = entry.trials.iter().find(|other| span.overlaps(other)) {
+                return Err(anyhow!(
+                    "`{run}` repeats trials {}..{} of seed {} for {} points, {} in dimension {}",
+                    span.start.max(repeated.start),
+                    span.end.min(repeated.end),
+                    span.seed.unwrap_or_default(),
+                    entry.key.points,
+                    entry.key.functional,
+                    entry.key.dimension
+                ));
+            }
+        }
+
+        for &span in spans {
+            // A resumed run carries on where it stopped
+            match entry
+                .trials
+                .iter_mut()
+                .find(|other| other.seed == span.seed && other.end == span.start)
+            {
+                Some(other) => other.end = span.end,
+                None => entry.trials.push(span),
+            }
+        }
+        entry.accumulator.merge(accumulator);
+        Ok(())
+    }
+
+    /// Merges every entry of `other`, unless they share some trials
+    pub fn merge(&mut self, other: Store) -> Result<()> {
+        for entry in other.entries {
+            self.add(&entry.run, entry.key, &entry.trials, &entry.accumulator)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Key, Span, Store};
+    use crate::{euclidean::PointProcess, statistics::Accumulator};
+
+    fn key(points: u32) -> Key {
+        Key {
+            dimension: 2,
+            points,
+            functional: "alpha=1".to_string(),
+            distribution: PointProcess::Uniform,
+            torus: false,
+            poisson: false,
+        }
+    }
+
+    fn span(seed: u64, start: u64, end: u64) -> Span {
+        Span {
+            seed: Some(seed),
+            start,
+            end,
+        }
+    }
+
+    #[test]
+    fn merge() {
+        let values: Vec<f64> = (0..30).map(|i| (i as f64).cos()).collect();
+        let whole: Accumulator = values.iter().copied().collect();
+
+        let mut first = Store::default();
+        let sample = values[..12].iter().copied().collect();
+        first.add("a", key(10), &[span(1, 0, 12)], &sample).unwrap();
+        let sample = [1.0, 2.0].into_iter().collect();
+        first.add("a", key(20), &[span(1, 0, 2)], &sample).unwrap();
+
+        let mut second = Store::default();
+        let sample = values[12..].iter().copied().collect();
+        second
+            .add("b", key(10), &[span(2, 0, 18)], &sample)
+            .unwrap();
+        let sample = [3.0].into_iter().collect();
+        second.add("b", key(30), &[span(2, 0, 1)], &sample).unwrap();
+
+        // Through the file format, which must keep every digit
+        let text = serde_json::to_string(&second).unwrap();
+        first.merge(serde_json::from_str(&text).unwrap()).unwrap();
+
+        assert_eq!(first.entries.len(), 3);
+        assert_eq!(first.entries[0].run, "a");
+        let merged = first.get(&key(10)).unwrap();
+        assert_eq!(merged.count, 30);
+        assert!((merged.mean - whole.mean).abs() < 1e-12);
+        assert!((merged.m2 - whole.m2).abs() < 1e-12);
+        assert_eq!(first.get(&key(30)).unwrap().count, 1);
+    }
+
+    #[test]
+    fn overlap() {
+        let sample: Accumulator = [1.0, 2.0, 3.0].into_iter().collect();
+        let mut store = Store::default();
+        store
+            .add("a", key(10), &[span(7, 0, 100)], &sample)
+            .unwrap();
+
+        // Resuming carries on the same span
+        store
+            .add("a", key(10), &[span(7, 100, 150)], &sample)
+            .unwrap();
+        assert_eq!(store.entries[0].trials, [span(7, 0, 150)]);
+
+        // Other seeds, or trials without a seed, are different trials
+        store
+            .add("b", key(10), &[span(8, 0, 100)], &sample)
+            .unwrap();
+        let unseeded = Span {
+            seed: None,
+            ..span(0, 0, 100)
+        };
+        store.add("c", key(10), &[unseeded], &sample).unwrap();
+        store.add("c", key(10), &[unseeded], &sample).unwrap();
+
+        // The same results merged twice would count every trial twice
+        let copy = store.clone();
+        assert!(store.merge(copy).is_err());
+        assert!(store
+            .add("d", key(10), &[span(7, 149, 160)], &sample)
+            .is_err());
+        assert_eq!(store.get(&key(10)).unwrap().count, 15);
+    }
+}
@@ -0,0 +1,142 @@
+use crate::{euclidean::PointProcess, store::Key};
+
+/// Apéry's constant, the limit of the total weight of the minimum spanning
+/// tree of the complete graph (Frieze)
+pub const ZETA_3: f64 = 1.202_056_903_159_594_3;
+
+/// Euler's constant
+const GAMMA: f64 = 0.577_215_664_901_532_9;
+
+/// A known value of a functional
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reference {
+    /// How the value reads in a sentence
+    pub name: &'static str,
+    pub value: f64,
+}
+
+impl Reference {
+    /// Distance of `mean` from the value, in units of `error`
+    pub fn deviation(&self, mean: f64, error: f64) -> f64 {
+        (mean - self.value) / error
+    }
+}
+
+/// The value the mean at `key.points` points is checked against: its exact
+/// expectation, or on the complete graph the limit, which the mean
+/// approaches as `O(1/n)` (Cooper, Frieze, Ince, Janson and Spencer)
+pub fn reference(key: &Key) -> Option<Reference> {
+    match key.dimension {
+        0 => limit(key),
+        _ => exact(key),
+    }
+}
+
+/// The exact expectation of the functional, known for uniform points on
+/// the line. Off the torus, the tree holds the `n-1` inner spacings of `n`
+/// uniform points, each of which has `E[g^α] = α!·n!/(n+α)!` for integer
+/// `α`. On the torus it holds all `n` spacings but the longest, which has
+/// mean `H_n/n`.
+pub fn exact(key: &Key) -> Option<Reference> {
+    if key.dimension != 1 || key.distribution != PointProcess::Uniform || key.poisson {
+        return None;
+    }
+
+    let alpha = alpha(&key.functional)?;
+    let n = key.points as f64;
+    let value = match key.torus {
+        false if alpha.fract() == 0.0 && alpha <= 64.0 => {
+            let moment: f64 = (1..=alpha as u32)
+                .map(|k| k as f64 / (n + k as f64))
+                .product();
+            (n - 1.0) * moment
+        }
+        true if alpha == 1.0 => 1.0 - harmonic(key.points) / n,
+        _ => return None,
+    };
+
+    Some(Reference {
+        name: "the exact value",
+        value,
+    })
+}
+
+/// The limit of the functional, for those which tend to a constant without
+/// being scaled. The means at finite sizes differ from it, so it is only
+/// meant for the constants extrapolated by `analyze`.
+pub fn limit(key: &Key) -> Option<Reference> {
+    match (key.dimension, alpha(&key.functional)?) {
+        (0, 1.0) => Some(Reference {
+            name: "the limit ζ(3)",
+            value: ZETA_3,
+        }),
+        (1, 1.0) if key.distribution == PointProcess::Uniform => Some(Reference {
+            name: "the limit 1",
+            value: 1.0,
+        }),
+        _ => None,
+    }
+}
+
+fn alpha(functional: &str) -> Option<f64> {
+    functional.strip_prefix("alpha=")?.parse().ok()
+}
+
+/// The harmonic number `H_n`, from its asymptotic expansion for large `n`
+fn harmonic(n: u32) -> f64 {
+    if n <= 1 << 12 {
+        return (1..=n).rev().map(|k| 1.0 / k as f64).sum();
+    }
+
+    let n = n as f64;
+    n.ln() + GAMMA + 1.0 / (2.0 * n) - 1.0 / (12.0 * n * n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{exact, harmonic, limit, reference, ZETA_3};
+    use crate::{euclidean::PointProcess, store::Key};
+
+    fn key(dimension: u32, points: u32, functional: &str, torus: bool) -> Key {
+        Key {
+            dimension,
+            points,
+            functional: functional.to_string(),
+            distribution: PointProcess::Uniform,
+            torus,
+            poisson: false,
+        }
+    }
+
+    #[test]
+    fn values() {
+        let line = exact(&key(1, 50, "alpha=1", false)).unwrap();
+        assert!((line.value - 49.0 / 51.0).abs() < 1e-15);
+        let squares = exact(&key(1, 50, "alpha=2", false)).unwrap();
+        assert!((squares.value - 49.0 * 2.0 / (51.0 * 52.0)).abs() < 1e-15);
+
+        // The expansion takes over from the sum past 2^12
+        let sum: f64 = (1..=10000).rev().map(|k| 1.0 / k as f64).sum();
+        assert!((harmonic(10000) - sum).abs() < 1e-12);
+
+        assert_eq!(limit(&key(0, 100, "alpha=1", false)).unwrap().value, ZETA_3);
+        assert!(limit(&key(0, 100, "alpha=2", false)).is_none());
+        // Only the limit is known for the complete graph and Poisson sizes
+        assert!(exact(&key(0, 100, "alpha=1", false)).is_none());
+        let poisson = Key {
+            poisson: true,
+            ..key(1, 50, "alpha=1", false)
+        };
+        assert!(exact(&poisson).is_none());
+        assert!(reference(&poisson).is_none());
+
+        // The complete graph is checked against the limit
+        let complete = reference(&key(0, 100, "alpha=1", false)).unwrap();
+        assert_eq!(complete.name, "the limit ζ(3)");
+        assert_eq!(complete.value, ZETA_3);
+        assert!(reference(&key(0, 100, "alpha=2", false)).is_none());
+        assert!(exact(&key(1, 50, "alpha=1.5", false)).is_none());
+        assert_eq!(limit(&key(1, 50, "alpha=1", true)).unwrap().value, 1.0);
+        assert!(exact(&key(2, 100, "alpha=1", false)).is_none());
+    }
+}
@@ -0,0 +1,8 @@
+/// An edge of a spanning tree, with its endpoints given as indices into the
+/// points (or vertices) the tree was built from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Edge {
+    pub u: u32,
+    pub v: u32,
+    pub length: f64,
+}
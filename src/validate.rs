@@ -0,0 +1,103 @@
+use average::{Estimate, MeanWithError};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use rand_distr::Distribution;
+
+use crate::{
+    complete,
+    euclidean::{self, reference::disagreement, Hypercube, Morton, Point, PointProcess},
+};
+
+/// Instances up to this many points are printed in full when they fail
+const PRINTED_POINTS: u32 = 32;
+
+/// Point processes the Euclidean algorithms are checked on
+const PROCESSES: [&str; 4] = ["uniform", "gaussian:0.1", "thomas:5,0.02", "beta:0.5,0.5"];
+
+/// Outcome of one self-check, with what went wrong if it failed
+pub struct Check {
+    pub name: String,
+    pub failure: Option<String>,
+}
+
+/// Compares the mean weight of the sampled complete trees with Kruskal on
+/// explicitly drawn uniform weights, which must agree within five standard
+/// errors
+pub fn complete(instances: u64, size: u32, seed: u64) -> Check {
+    let name = format!("complete graph on {size} vertices");
+    let (mut sampled, mut explicit) = (MeanWithError::new(), MeanWithError::new());
+
+    for i in seed..seed + instances {
+        match complete::mst(size, SmallRng::seed_from_u64(i)) {
+            Ok(total) => sampled.add(total),
+            Err(err) => {
+                return Check {
+                    name,
+                    failure: Some(err.to_string()),
+                }
+            }
+        }
+
+        let weights = complete::reference::uniform_weights(size, SmallRng::seed_from_u64(i));
+        let edges = complete::reference::kruskal(size, weights);
+        explicit.add(edges.iter().map(|edge| edge.length).sum());
+    }
+
+    let error = (sampled.error().powi(2) + explicit.error().powi(2)).sqrt();
+    let deviation = (sampled.mean() - explicit.mean()).abs() / error;
+    Check {
+        name,
+        failure: (deviation >= 5.0).then(|| {
+            format!(
+                "sampled mean {:.6} against explicit {:.6} ({deviation:.1} standard errors)",
+                sampled.mean(),
+                explicit.mean()
+            )
+        }),
+    }
+}
+
+/// Runs every algorithm and Kruskal strategy against Prim on point sets of
+/// up to `max_size` points, drawn from several point processes
+pub fn euclidean<const D: usize>(instances: u64, max_size: u32, seed: u64) -> Check
+where
+    Hypercube<D>: Distribution<Point<D>>,
+    Point<D>: Morton,
+{
+    let failure = (seed..seed + instances).find_map(|i| {
+        let mut rng = SmallRng::seed_from_u64(i);
+        let size = rng.gen_range(1..=max_size);
+        let process: PointProcess = PROCESSES[i as usize % PROCESSES.len()].parse().unwrap();
+        let points: Vec<Point<D>> = euclidean::sample_points::<D>(size, &mut rng, &process)
+            .into_iter()
+            .map(Point::from_float)
+            .collect();
+
+        disagreement(&points).map(|name| {
+            let failure =
+                format!("{name} disagrees with prim on {size} {process} points (seed {i})");
+            match size <= PRINTED_POINTS {
+                true => {
+                    let points: Vec<[f64; D]> = points.iter().map(|p| p.to_float()).collect();
+                    format!("{failure}: {points:?}")
+                }
+                false => failure,
+            }
+        })
+    });
+
+    Check {
+        name: format!("euclidean algorithms in dimension {D}"),
+        failure,
+    }
+}
+
+/// Every self-check, in order
+pub fn all(instances: u64, max_size: u32, seed: u64) -> Vec<Check> {
+    vec![
+        complete(instances, max_size, seed),
+        euclidean::<1>(instances, max_size, seed),
+        euclidean::<2>(instances, max_size, seed),
+        euclidean::<3>(instances, max_size, seed),
+        euclidean::<4>(instances, max_size, seed),
+    ]
+}